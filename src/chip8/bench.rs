@@ -0,0 +1,109 @@
+use crate::chip8::cpu::cpu::Profile;
+use crate::chip8::machine::Machine;
+use crate::chip8::ChipSettings;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BenchLimit {
+    Instructions(u64),
+    Seconds(f64),
+}
+
+pub struct BenchReport {
+    rom: String,
+    cpu_rate: u64,
+    instructions: u64,
+    frames: u64,
+    elapsed: Duration,
+    // from a second, profiled run of the same number of instructions
+    profile: Profile,
+    profiled_instructions: u64,
+}
+
+// runs the machine headless with no sleeping at all, frame by frame like everything else
+pub struct Bench {
+    settings: ChipSettings,
+}
+
+impl Bench {
+    pub fn new(settings: ChipSettings) -> Bench {
+        Bench { settings }
+    }
+
+    pub fn run(&self, limit: BenchLimit) -> BenchReport {
+        let mut machine = Machine::new(&self.settings);
+        if let BenchLimit::Instructions(count) = limit {
+            machine.set_instruction_limit(count);
+        }
+        let start = Instant::now();
+        loop {
+            let done = match limit {
+                BenchLimit::Instructions(count) => machine.instructions() >= count,
+                BenchLimit::Seconds(seconds) => start.elapsed().as_secs_f64() >= seconds,
+            };
+            // nothing presses keys here, a ROM waiting for one would keep the benchmark running forever
+            if done || machine.is_waiting_for_key() {
+                break;
+            }
+            machine.run_frame();
+        }
+        let elapsed = start.elapsed();
+        if machine.is_waiting_for_key() {
+            log::warn!("The ROM waits for a key, the benchmark stopped after {} instructions", machine.instructions());
+        }
+
+        // timing every step is slow, so it gets a run of its own instead of skewing the numbers above
+        let mut profiled = Machine::new(&self.settings);
+        profiled.start_profiling();
+        profiled.set_instruction_limit(machine.instructions());
+        while profiled.instructions() < machine.instructions() && !profiled.is_waiting_for_key() {
+            profiled.run_frame();
+        }
+
+        BenchReport {
+            rom: self.settings.rom_name().to_string(),
+            cpu_rate: self.settings.cpu_rate,
+            instructions: machine.instructions(),
+            frames: machine.frame(),
+            elapsed,
+            profile: profiled.profile().unwrap_or_default(),
+            profiled_instructions: profiled.instructions(),
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        writeln!(f, "{} at {} Hz", self.rom, self.cpu_rate)?;
+        writeln!(f, "  instructions  {} in {:.2} s", self.instructions, seconds)?;
+        writeln!(
+            f,
+            "  speed         {:.0} instructions/s ({:.1}x real time)",
+            self.instructions as f64 / seconds,
+            self.frames as f64 / 60.0 / seconds
+        )?;
+        writeln!(f, "  frames        {} ({:.0} fps)", self.frames, self.frames as f64 / seconds)?;
+
+        let steps = [
+            ("fetch", self.profile.fetch),
+            ("decode", self.profile.decode),
+            ("execute", self.profile.execute),
+            ("drw/cls", self.profile.draw),
+        ];
+        let total = steps.iter().map(|(_, time)| time.as_secs_f64()).sum::<f64>().max(f64::EPSILON);
+        let instructions = self.profiled_instructions.max(1) as f64;
+        writeln!(f, "  per instruction, from a profiled run:")?;
+        for (name, time) in steps {
+            writeln!(
+                f,
+                "    {:<8} {:>8.1} ns  {:>5.1} %",
+                name,
+                time.as_secs_f64() * 1e9 / instructions,
+                time.as_secs_f64() / total * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,219 +1,711 @@
-use crate::chip8::cpu::Cpu;
-use crate::chip8::io::display::Display;
-use crate::chip8::io::keyboard::Keyboard;
-use crate::chip8::ram::Ram;
-use raylib::prelude::RaylibDraw;
-use std::cell::RefCell;
-use std::cmp::min;
-use std::rc::Rc;
+use crate::chip8::config::SettingsLayer;
+use crate::chip8::cpu::rng::RngKind;
+use crate::chip8::cpu::timing::{Timing, CLOCKS_PER_MACHINE_CYCLE, INTERRUPT_CYCLES, VIP_CLOCK};
+use crate::chip8::debugger::DebugView;
+use crate::chip8::io::beeper::{Beeper, BeeperSettings};
+use crate::chip8::io::debug_panels::{DebugPanels, PANELS_WIDTH};
+use crate::chip8::io::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::chip8::io::filter::{DisplayFilter, ScreenFilter};
+use crate::chip8::io::gamepad::Gamepads;
+use crate::chip8::io::keymap::{Action, Keymap};
+use crate::chip8::io::overlay::{Overlay, Status};
+use crate::chip8::io::palette::{parse_color, Colors};
+use crate::chip8::io::raylib_input::RaylibInput;
+use crate::chip8::io::scaling::{Scaling, Viewport};
+use crate::chip8::machine::{Machine, SaveState};
+use crate::chip8::platform::Platform;
+use crate::chip8::record::{GifRecorder, Movie};
+use crate::chip8::scheduler::{FrameClock, FRAME_RATE};
+use raylib::prelude::{
+    Color, Image, MouseButton, RaylibAudio, RaylibDraw, RaylibTexture2D, Rectangle, Texture2D, Vector2,
+};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44100;
+// samples handed to the audio device at once, ~12 ms - the tone follows the sound timer with this latency
+const AUDIO_BUFFER_SIZE: usize = 512;
+// how often the audio stream is checked while waiting for the next frame, well below a buffer's length
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(2);
+// one screen pixel per chip-8 pixel
+const MIN_WINDOW_SIZE: (u32, u32) = (DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32);
+// grid lines are only drawn when pixels are at least this large, below that they would eat the image
+const MIN_GRID_PIXEL_SIZE: f32 = 4.0;
 
 // chip settings
 pub struct ChipSettings {
-    rom: String,
+    pub(crate) rom: String,
     size: (u32, u32),
-    cpu_rate: u64,
-    timers_rate: u64,
-    colors: (u32, u32),
-    swap_yz: bool,
-    beep: bool,
+    scaling: Scaling,
+    pixel_aspect: f32,
+    grid: bool,
+    fullscreen: bool,
+    overlay: bool,
+    debugger: bool,
+    // multipliers on the frame rate, see FrameClock::tick
+    fast_forward_speed: f32,
+    slow_motion_speed: f32,
+    pub(crate) cpu_rate: u64,
+    pub(crate) timers_rate: u64,
+    platform: Platform,
+    pub(crate) timing: Timing,
+    pub(crate) display_wait: bool,
+    pub(crate) colors: Colors,
+    pub(crate) filter: DisplayFilter,
+    pub(crate) phosphor_frames: u32,
+    pub(crate) keymap: Keymap,
+    pub(crate) beeper: BeeperSettings,
+    pub(crate) rng: RngKind,
+    pub(crate) seed: u64,
 }
 
 impl ChipSettings {
-    pub fn new(
-        rom: String,
-        size: (u32, u32),
-        cpu_rate: u64,
-        timers_rate: u64,
-        colors: (u32, u32),
-        swap_yz: bool,
-        beep: bool,
-    ) -> ChipSettings {
-        let size = ChipSettings::fix_size(size);
+    pub fn builder(rom: String) -> ChipSettingsBuilder {
+        ChipSettingsBuilder {
+            rom,
+            layer: SettingsLayer::default(),
+        }
+    }
 
-        let colors = ChipSettings::fix_colors(colors.0, colors.1);
+    // the window starts at the display's shape, it can be resized and letterboxed later
+    pub(crate) fn fix_size(size: (u32, u32), pixel_aspect: f32) -> (u32, u32) {
+        let ratio = DISPLAY_WIDTH as f32 * pixel_aspect / DISPLAY_HEIGHT as f32;
+        let (width, height) = match size {
+            (0, 0) => (640, (640.0 / ratio) as u32),
+            (width, 0) => (width, (width as f32 / ratio) as u32),
+            (0, height) => ((height as f32 * ratio) as u32, height),
+            size => size,
+        };
+        (width.max(MIN_WINDOW_SIZE.0), height.max(MIN_WINDOW_SIZE.1))
+    }
 
-        if cpu_rate / timers_rate != 500 / 60 {
-            log::warn!("CPU rate and timers rate are not in the correct ratio (500/60), this may cause issues.");
+    fn fix_colors(colors: Colors) -> Colors {
+        if colors[0] == colors[1] {
+            log::warn!(
+                "Foreground and background colors are the same. Enjoy your invisible screen!"
+            );
         }
+        colors
+    }
 
-        ChipSettings {
-            rom,
-            size,
-            cpu_rate,
-            timers_rate,
-            colors,
-            swap_yz,
-            beep,
-        }
+    pub fn rom_name(&self) -> &str {
+        rom_name(&self.rom)
     }
+}
 
-    fn fix_size(size: (u32, u32)) -> (u32, u32) {
-        match (size.0, size.1) {
-            (0, 0) => {
-                (640, 320)
-            }
-            (width, height) if width != 0 && height != 0 => {
-                if width / height != 2 {
-                    log::warn!("Width and height are not in the correct ratio (64/32). This may cause some (visual) issues. Set just one of them or try to find a correct ratio.");
-                }
-                (width, height)
+// stacks settings layers, e.g. config file defaults, then the rom's section, then the command line
+pub struct ChipSettingsBuilder {
+    rom: String,
+    layer: SettingsLayer,
+}
+
+impl ChipSettingsBuilder {
+    // anything set in the layer overrides the layers added before it
+    pub fn layer(mut self, layer: &SettingsLayer) -> ChipSettingsBuilder {
+        self.layer.merge(layer);
+        self
+    }
+
+    pub fn build(self) -> Result<ChipSettings, String> {
+        let layer = self.layer;
+
+        let pixel_aspect = layer.pixel_aspect.unwrap_or(1.0);
+        if !(pixel_aspect.is_finite() && pixel_aspect > 0.0) {
+            return Err(format!("Invalid pixel aspect {}, it has to be above 0", pixel_aspect));
+        }
+        let size = ChipSettings::fix_size(
+            (layer.width.unwrap_or(0), layer.height.unwrap_or(0)),
+            pixel_aspect,
+        );
+        let scaling = layer.scaling.unwrap_or_default();
+
+        // --fg and --bg replace the palette's first two colours
+        let mut colors = layer.palette.unwrap_or_default().colors();
+        if let Some(fg_color) = &layer.fg_color {
+            colors[1] = parse_color(fg_color)?;
+            if layer.palette.is_none() {
+                colors[0] = !colors[1] & 0xFFFFFF;
             }
-            (width, 0) => {
-                let width = width - width % 64;
-                let height = width / 2;
-                (width, height)
+        }
+        if let Some(bg_color) = &layer.bg_color {
+            colors[0] = parse_color(bg_color)?;
+        }
+        let colors = ChipSettings::fix_colors(colors);
+        let filter = layer.filter.unwrap_or_default();
+        let phosphor_frames = layer.phosphor_frames.unwrap_or(5);
+        let grid = layer.grid.unwrap_or(false);
+        let fullscreen = layer.fullscreen.unwrap_or(false);
+        let overlay = layer.overlay.unwrap_or(false);
+        let debugger = layer.debugger.unwrap_or(false);
+        let fast_forward_speed = layer.fast_forward_speed.unwrap_or(4.0);
+        let slow_motion_speed = layer.slow_motion_speed.unwrap_or(0.25);
+        for speed in [fast_forward_speed, slow_motion_speed] {
+            if !(speed.is_finite() && speed > 0.0) {
+                return Err(format!("Invalid speed {}, it has to be above 0", speed));
             }
-            (0, height) => {
-                let height = height - height % 32;
-                let width = height * 2;
-                (width, height)
+        }
+
+        let platform = layer.platform.unwrap_or_default();
+        let timing = layer.timing.unwrap_or(platform.default_timing());
+        let display_wait = layer.display_wait.unwrap_or(platform.default_display_wait());
+        let cpu_rate = match timing {
+            // octo's tickrate is instructions per frame, the same thing as a clock of tickrate * 60 Hz
+            Timing::Instructions => match layer.tickrate {
+                Some(tickrate) => tickrate as u64 * FRAME_RATE as u64,
+                None => layer.cpu_clock.unwrap_or(500) as u64,
+            },
+            Timing::Vip => {
+                if layer.tickrate.is_some() {
+                    log::warn!("VIP timing has no fixed tickrate, use --vip-clock to change the 1802 clock");
+                }
+                let clock = layer.vip_clock.map(|clock| clock as u64).unwrap_or(VIP_CLOCK);
+                if clock / (CLOCKS_PER_MACHINE_CYCLE * FRAME_RATE as u64) <= INTERRUPT_CYCLES {
+                    return Err(format!(
+                        "A {} Hz clock is too slow for VIP timing, the display alone takes longer than a frame. The VIP ran at {} Hz.",
+                        clock, VIP_CLOCK
+                    ));
+                }
+                clock
             }
-            _ => unreachable!("Rust is lying to me"),
+        };
+        // nothing would ever run, the benchmark would wait forever
+        if cpu_rate == 0 {
+            return Err("The CPU clock and tickrate have to be above 0".to_string());
         }
+        let timers_rate = match layer.timers_rate {
+            Some(rate) if rate != 0 => rate as u64,
+            _ => FRAME_RATE as u64,
+        };
+        if timers_rate != FRAME_RATE as u64 {
+            log::warn!("Timers run at {} Hz instead of once per frame, this may cause issues.", timers_rate);
+        }
+
+        let layout = layer.layout.unwrap_or_default();
+        let keymap = match &layer.keymap {
+            Some(path) => Keymap::load(path, layout, rom_name(&self.rom))?,
+            None => Keymap::new(layout),
+        };
+
+        let beeper = BeeperSettings {
+            frequency: layer.beep_frequency.unwrap_or(440.0),
+            volume: layer.volume.unwrap_or(25).min(100) as f32 / 100f32,
+            waveform: layer.waveform.unwrap_or_default(),
+            muted: layer.mute.unwrap_or(false),
+        };
+
+        let rng = layer.rng.unwrap_or(platform.default_rng());
+        // without a seed every run gets a different one, it is logged so the run can be repeated
+        let seed = layer.seed.unwrap_or_else(rand::random);
+        log::info!("RND uses {:?} with seed {}", rng, seed);
+
+        Ok(ChipSettings {
+            rom: self.rom,
+            size,
+            scaling,
+            pixel_aspect,
+            grid,
+            fullscreen,
+            overlay,
+            debugger,
+            fast_forward_speed,
+            slow_motion_speed,
+            cpu_rate,
+            timers_rate,
+            platform,
+            timing,
+            display_wait,
+            colors,
+            filter,
+            phosphor_frames,
+            keymap,
+            beeper,
+            rng,
+            seed,
+        })
     }
+}
 
-    fn fix_colors(fg_color: u32, bg_color: u32) -> (u32, u32) {
-        if fg_color == bg_color {
-            log::warn!(
-                "Foreground and background colors are the same. Enjoy your invisible screen!"
-            );
-        }
-        (fg_color, bg_color)
+// rom file name without the directory and extension, also what per-rom settings are keyed by
+pub fn rom_name(rom: &str) -> &str {
+    std::path::Path::new(rom)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or("")
+}
+
+// a raylib window, kept open between the rom menu and the games started from it
+pub type Window = (raylib::RaylibHandle, raylib::RaylibThread);
+
+pub fn open_window(size: (u32, u32), title: &str, fullscreen: bool) -> Window {
+    let (mut rl, thread) = raylib::init()
+        .size(size.0 as i32, size.1 as i32)
+        .title(title)
+        .resizable()
+        .build();
+    rl.set_window_min_size(MIN_WINDOW_SIZE.0 as i32, MIN_WINDOW_SIZE.1 as i32);
+    if fullscreen {
+        rl.toggle_borderless_windowed();
     }
+    (rl, thread)
+}
+
+// why Chip8::run returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Closed,
+    Menu,
 }
 
 pub struct Chip8 {
-    cpu: Cpu,
-    ram: Rc<RefCell<Ram>>,
-    display: Rc<RefCell<Display>>,
-    keyboard: Rc<RefCell<Keyboard>>,
+    machine: Machine,
     rl: (raylib::RaylibHandle, raylib::RaylibThread),
-    pixel_size: (i32, i32),
     settings: ChipSettings,
+    beeper: Beeper,
+    gamepads: Gamepads,
+    screen: ScreenFilter,
+    overlay: Overlay,
+    // the debugger panels are open
+    debugger: bool,
+    // the screen as uploaded to the gpu, with the display size it was made for
+    texture: Option<(Texture2D, (usize, usize))>,
+    // draw the next frame even if the picture didn't change
+    redraw: bool,
+    gif: Option<GifRecorder>,
+    gif_count: u32,
+    movie: Option<Movie>,
+    save_state: Option<SaveState>,
+    paused: bool,
+    // run one frame even though paused
+    frame_advance: bool,
+    // held or toggled, see handle_input
+    fast_forward: bool,
+    fast_forward_toggled: bool,
+    slow_motion: bool,
+    // started from the rom menu, which the Menu action goes back to
+    menu: bool,
+    back_to_menu: bool,
 }
 
 impl Chip8 {
     pub fn new(settings: ChipSettings) -> Chip8 {
-        let display = Rc::new(RefCell::new(Display::new()));
-        let keyboard = Rc::new(RefCell::new(Keyboard::new(settings.swap_yz)));
-        let ram = Rc::new(RefCell::new(Ram::new()));
-        ram.borrow_mut().load_rom(settings.rom.as_str());
+        // make title the name of the rom (extracted from the path)
+        let title = format!("Rusty Chip-8: {}", settings.rom_name());
+        let window = open_window(settings.size, &title, settings.fullscreen);
+        Chip8::with_window(settings, window)
+    }
 
-        let cpu = Cpu::new(display.clone(), ram.clone(), keyboard.clone());
+    // the window is the menu's, Esc goes back to the menu instead of closing it
+    pub fn from_menu(settings: ChipSettings, mut window: Window) -> Chip8 {
+        window.0.set_window_title(&window.1, &format!("Rusty Chip-8: {}", settings.rom_name()));
+        window.0.set_exit_key(None);
+        let mut chip8 = Chip8::with_window(settings, window);
+        chip8.menu = true;
+        chip8
+    }
 
-        // make title the name of the rom (extracted from the path)
-        let name = settings
-            .rom
-            .split('/')
-            .last()
-            .unwrap_or("")
-            .split('.')
-            .next()
-            .unwrap_or("");
-        let (rl, thread) = raylib::init()
-            .size(settings.size.0 as i32, settings.size.1 as i32)
-            .title(&format!("Rusty Chip-8: {}", name))
-            .build();
-        
-        let pixel_size = (settings.size.0 as i32 / 64, settings.size.1 as i32 / 32);
+    // the window outlives the game, see Launcher
+    pub fn into_window(self) -> Window {
+        self.rl
+    }
+
+    fn with_window(settings: ChipSettings, rl: Window) -> Chip8 {
+        let machine = Machine::new(&settings);
+        let beeper = Beeper::new(settings.beeper, SAMPLE_RATE);
+        let gamepads = Gamepads::new(settings.keymap.gamepad.clone());
+        let screen = ScreenFilter::new(settings.filter, settings.colors, settings.phosphor_frames);
+        let overlay = Overlay::new(settings.overlay);
+        let debugger = settings.debugger;
 
         Chip8 {
-            cpu,
-            ram,
-            display,
-            keyboard,
-            rl: (rl, thread),
-            pixel_size,
+            machine,
+            rl,
             settings,
+            beeper,
+            gamepads,
+            screen,
+            overlay,
+            debugger,
+            texture: None,
+            redraw: true,
+            gif: None,
+            gif_count: 0,
+            movie: None,
+            save_state: None,
+            paused: false,
+            frame_advance: false,
+            fast_forward: false,
+            fast_forward_toggled: false,
+            slow_motion: false,
+            menu: false,
+            back_to_menu: false,
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn run(&mut self) -> Exit {
+        // the stream borrows the audio device, both have to live here rather than in self
+        let audio = if self.settings.beeper.muted {
+            None
+        } else {
+            RaylibAudio::init_audio_device()
+                .map_err(|err| {
+                    log::error!("Failed to initialize audio device, sound disabled: {}", err)
+                })
+                .ok()
+        };
+        let mut stream = audio.as_ref().map(|audio| {
+            // the stream buffer has to match what we write, otherwise raylib pads it with silence
+            unsafe { raylib::ffi::SetAudioStreamBufferSizeDefault(AUDIO_BUFFER_SIZE as i32) };
+            let mut stream = audio.new_audio_stream(SAMPLE_RATE, 32, 1);
+            stream.play();
+            stream
+        });
+        let mut samples = [0f32; AUDIO_BUFFER_SIZE];
+
+        let mut clock = FrameClock::new();
+        while !self.rl.0.window_should_close() && !self.back_to_menu {
+            if let Some(stream) = &mut stream {
+                if stream.is_processed() {
+                    let beep = self.machine.should_beep() && !self.paused;
+                    self.beeper.fill(&mut samples, beep);
+                    stream.update(&samples);
+                }
+            }
+
+            // a frame is longer than an audio buffer, so wait in slices and keep the stream fed
+            let remaining = clock.remaining();
+            if !remaining.is_zero() {
+                std::thread::sleep(remaining.min(AUDIO_POLL_INTERVAL));
+                continue;
+            }
+
+            clock.tick(self.speed());
+            self.run_frame();
+        }
+        self.stop_gif_recording();
+        self.stop_movie();
+        if self.back_to_menu {
+            Exit::Menu
+        } else {
+            Exit::Closed
+        }
+    }
+
+    // fast forward wins over slow motion when both are on
+    fn speed(&self) -> f64 {
+        if self.fast_forward || self.fast_forward_toggled {
+            self.settings.fast_forward_speed as f64
+        } else if self.slow_motion {
+            self.settings.slow_motion_speed as f64
+        } else {
+            1.0
+        }
+    }
+
+    // input, cpu and timers, then the screen, each exactly once per frame
+    fn run_frame(&mut self) {
+        self.handle_input();
+        let running = !self.paused || std::mem::take(&mut self.frame_advance);
+        if self.movie.is_some() {
+            self.run_movie_frame(running);
+        } else if running {
+            self.machine.run_frame();
+        }
+        if let Some(address) = self.machine.take_breakpoint_hit() {
+            self.paused = true;
+            self.overlay.show_message(&format!("Breakpoint at {:03X}", address));
         }
+        let dirty_rows = self.machine.take_dirty_rows();
+        let changed = self.screen.present(&self.machine.display(), dirty_rows);
+        let overlay = self.overlay.update(self.machine.frame(), self.machine.instructions());
+        self.draw(changed, overlay || self.debugger);
+        self.capture_gif_frame();
     }
 
-    pub fn run(&mut self) {
-        let mut next_time_to_cycle = std::time::Instant::now();
-        let mut next_time_to_timer = std::time::Instant::now();
-        let timers_rate = self.settings.timers_rate;
-        let cpu_rate = self.settings.cpu_rate;
-        while !self.rl.0.window_should_close() {
-            if std::time::Instant::now() < next_time_to_cycle
-                && std::time::Instant::now() < next_time_to_timer
-            {
-                std::thread::sleep(
-                    min(
-                        next_time_to_cycle.duration_since(std::time::Instant::now()),
-                        next_time_to_timer.duration_since(std::time::Instant::now()),
-                    ),
-                );
+    pub fn start_movie(&mut self, movie: Movie) {
+        self.movie = Some(movie);
+    }
+
+    fn stop_movie(&mut self) {
+        if let Some(movie) = self.movie.take() {
+            if let Err(err) = movie.finish(&self.machine) {
+                log::error!("{}", err);
             }
-            if std::time::Instant::now() >= next_time_to_cycle {
-                next_time_to_cycle =
-                    std::time::Instant::now() + std::time::Duration::from_millis(1000 / cpu_rate);
-                self.cpu.cycle();
-                self.handle_input();
-                self.draw();
+        }
+    }
+
+    fn run_movie_frame(&mut self, running: bool) {
+        let Some(movie) = &mut self.movie else {
+            return;
+        };
+
+        // the keys are taken even when the frame doesn't run, the keyboard only keeps the latest edges
+        movie.before_frame(&self.machine);
+        if !running {
+            return;
+        }
+        self.machine.run_frame();
+        movie.after_frame(&self.machine);
+
+        if movie.is_finished(&self.machine) {
+            log::info!("Movie finished");
+            self.stop_movie();
+        }
+    }
+
+    pub fn start_gif_recording(&mut self, path: &str) {
+        self.stop_gif_recording();
+        match GifRecorder::new(path, self.settings.colors) {
+            Ok(recorder) => {
+                log::info!("Recording GIF to {}", path);
+                self.gif = Some(recorder);
             }
-            if std::time::Instant::now() >= next_time_to_timer {
-                next_time_to_timer = std::time::Instant::now()
-                    + std::time::Duration::from_millis(1000 / timers_rate);
-                self.cpu.update_timers();
+            Err(err) => log::error!("Failed to start GIF recording to {}: {}", path, err),
+        }
+    }
+
+    pub fn stop_gif_recording(&mut self) {
+        if let Some(recorder) = self.gif.take() {
+            let path = recorder.path().to_string();
+            match recorder.finish() {
+                Ok(()) => log::info!("GIF saved to {}", path),
+                Err(err) => log::error!("Failed to save GIF {}: {}", path, err),
             }
         }
     }
 
-    fn draw(&mut self) {
-        let display = self.display.borrow();
-        let display = display.get_display();
+    fn toggle_gif_recording(&mut self) {
+        if self.gif.is_some() {
+            self.stop_gif_recording();
+        } else {
+            self.gif_count += 1;
+            let path = format!("{}-{}.gif", self.settings.rom_name(), self.gif_count);
+            self.start_gif_recording(&path);
+        }
+    }
+
+    fn capture_gif_frame(&mut self) {
+        if let Some(recorder) = &mut self.gif {
+            if let Err(err) = recorder.capture(&self.machine.display()) {
+                log::error!("GIF recording failed: {}", err);
+                self.gif = None;
+            }
+        }
+    }
+
+    // changed is whether the picture changed since the last frame, if it didn't, the window
+    // wasn't resized and there is no overlay on top either the frame is skipped
+    fn draw(&mut self, changed: bool, overlay: bool) {
+        let speed = self.speed();
+        let (columns, rows) = self.machine.display().size();
+        // the texture follows the display, e.g. when it switches between lores and hires
+        if self.texture.as_ref().is_none_or(|(_, size)| *size != (columns, rows)) {
+            let image = Image::gen_image_color(columns as i32, rows as i32, Color::BLACK);
+            match self.rl.0.load_texture_from_image(&self.rl.1, &image) {
+                Ok(texture) => self.texture = Some((texture, (columns, rows))),
+                Err(err) => {
+                    log::error!("Failed to create the screen texture: {}", err);
+                    return;
+                }
+            }
+            self.redraw = true;
+        }
+        let Some((texture, _)) = &mut self.texture else {
+            return;
+        };
+
+        if changed || self.redraw {
+            texture.update_texture(self.screen.rgba());
+        } else if !overlay && !self.rl.0.is_window_resized() {
+            // nothing new to show, but raylib only reads input when a frame is drawn
+            unsafe { raylib::ffi::PollInputEvents() };
+            return;
+        }
+        self.redraw = false;
+
+        // recomputed every frame, the window may have been resized or the display changed size
+        let window = (self.rl.0.get_screen_width(), self.rl.0.get_screen_height());
+        let panels = self.debugger.then(|| DebugPanels::new(window));
+        // the game is letterboxed into what the panels leave of the window
+        let game_area = match panels {
+            Some(_) => (window.0 - PANELS_WIDTH, window.1),
+            None => window,
+        };
+        let viewport = Viewport::new(
+            game_area,
+            (columns, rows),
+            self.settings.scaling,
+            self.settings.pixel_aspect,
+        );
+
         let mut handle = self.rl.0.begin_drawing(&self.rl.1);
-        for y in 0..32 {
-            for x in 0..64 {
-                if display[x + y * 64] == 1 {
-                    handle.draw_rectangle(
-                        x as i32 * self.pixel_size.0,
-                        y as i32 * self.pixel_size.1,
-                        self.pixel_size.0,
-                        self.pixel_size.1,
-                        raylib::prelude::Color::new(
-                            (self.settings.colors.0 >> 16) as u8,
-                            (self.settings.colors.0 >> 8) as u8,
-                            self.settings.colors.0 as u8,
-                            255,
-                        ),
-                    );
+        handle.clear_background(Color::BLACK);
+        handle.draw_texture_pro(
+            &*texture,
+            Rectangle::new(0.0, 0.0, columns as f32, rows as f32),
+            Rectangle::new(
+                viewport.x,
+                viewport.y,
+                viewport.width() as f32,
+                viewport.height() as f32,
+            ),
+            Vector2::new(0.0, 0.0),
+            0.0,
+            Color::WHITE,
+        );
+
+        let grid_visible = viewport.pixel_width.min(viewport.pixel_height) >= MIN_GRID_PIXEL_SIZE;
+        if self.settings.grid && grid_visible {
+            // in the background colour, so lit pixels look like separate dots
+            let color = Color::get_color((self.settings.colors[0] << 8) | 0xFF);
+            let (left, top) = (viewport.x as i32, viewport.y as i32);
+            let (right, bottom) = (left + viewport.width(), top + viewport.height());
+            let (columns, rows) = viewport.grid_lines();
+            for x in columns {
+                handle.draw_line(x, top, x, bottom, color);
+            }
+            for y in rows {
+                handle.draw_line(left, y, right, y, color);
+            }
+        }
+
+        let status = Status {
+            rom: self.settings.rom_name(),
+            platform: self.settings.platform.name(),
+            paused: self.paused,
+            speed,
+            keyboard: &self.machine.keyboard().borrow(),
+        };
+        self.overlay.draw(&mut handle, &status, game_area);
+
+        if let Some(panels) = panels {
+            let view = DebugView::new(&self.machine, panels.disassembly_lines(), panels.memory_rows());
+            panels.draw(&mut handle, &view);
+        }
+    }
+
+    fn trigger(&mut self, action: Action) {
+        // a movie only has the keypad, anything else that moves the machine would desync it
+        let moves_machine = matches!(
+            action,
+            Action::Reset | Action::SoftReset | Action::LoadState | Action::Step
+        );
+        if moves_machine && self.movie.is_some() {
+            log::warn!("{:?} is disabled while a movie is recorded or played", action);
+            self.overlay.show_message("Not available during a movie");
+            return;
+        }
+        match action {
+            Action::Pause => self.paused = !self.paused,
+            // the first press stops a running emulation, every press after that runs a frame
+            Action::FrameAdvance => {
+                if self.paused {
+                    self.frame_advance = true;
+                } else {
+                    self.paused = true;
+                }
+            }
+            Action::Reset => {
+                // breakpoints belong to the debugging session, not to the machine
+                let breakpoints = self.machine.breakpoints().clone();
+                self.machine = Machine::new(&self.settings);
+                for address in breakpoints {
+                    self.machine.toggle_breakpoint(address);
+                }
+            }
+            Action::SoftReset => self.machine.soft_reset(&self.settings),
+            Action::SaveState => {
+                self.save_state = Some(self.machine.save_state());
+                self.overlay.show_message("State saved");
+            }
+            Action::LoadState => match &self.save_state {
+                Some(state) => {
+                    self.machine.load_state(state);
+                    self.overlay.show_message("State loaded");
+                }
+                None => {
+                    log::warn!("No state saved yet");
+                    self.overlay.show_message("No state saved yet");
+                }
+            },
+            // held, not pressed - see handle_input
+            Action::FastForward => {}
+            Action::ToggleFastForward => self.fast_forward_toggled = !self.fast_forward_toggled,
+            Action::SlowMotion => self.slow_motion = !self.slow_motion,
+            Action::RecordGif => self.toggle_gif_recording(),
+            // borderless rather than exclusive, the desktop resolution stays as it is
+            Action::Fullscreen => self.rl.0.toggle_borderless_windowed(),
+            Action::Debugger => {
+                self.debugger = !self.debugger;
+                self.redraw = true;
+            }
+            // like frame advance, the first press only pauses
+            Action::Step => {
+                if self.paused {
+                    self.machine.step();
                 } else {
-                    handle.draw_rectangle(
-                        x as i32 * self.pixel_size.0,
-                        y as i32 * self.pixel_size.1,
-                        self.pixel_size.0,
-                        self.pixel_size.1,
-                        raylib::prelude::Color::new(
-                            (self.settings.colors.1 >> 16) as u8,
-                            (self.settings.colors.1 >> 8) as u8,
-                            self.settings.colors.1 as u8,
-                            255,
-                        ),
-                    );
+                    self.paused = true;
                 }
             }
+            // without a menu Esc closes the window anyway
+            Action::Menu => self.back_to_menu = self.menu,
+            Action::Overlay => {
+                self.overlay.visible = !self.overlay.visible;
+                // the frame it was on has to be drawn over
+                self.redraw = true;
+            }
         }
+    }
 
-        // beep
-        // if self.cpu.should_beep() {
-        //
-        // }
+    fn toggle_breakpoint_under_mouse(&mut self) {
+        let window = (self.rl.0.get_screen_width(), self.rl.0.get_screen_height());
+        let panels = DebugPanels::new(window);
+        let view = DebugView::new(&self.machine, panels.disassembly_lines(), panels.memory_rows());
+        let mouse = self.rl.0.get_mouse_position();
+        if let Some(address) = panels.line_at(&view, (mouse.x, mouse.y)) {
+            self.machine.toggle_breakpoint(address);
+        }
     }
 
     fn handle_input(&mut self) {
-        let mut keyboard = self.keyboard.borrow_mut();
-
-        self.rl.0.get_key_pressed().iter().for_each(|key| {
-            keyboard.press_key(*key);
-        });
+        let pressed = self
+            .settings
+            .keymap
+            .actions
+            .iter()
+            .filter(|(key, _)| self.rl.0.is_key_pressed(**key))
+            .map(|(_, action)| *action)
+            .collect::<Vec<Action>>();
+        for action in pressed {
+            self.trigger(action);
+        }
+        if self.debugger && self.rl.0.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            self.toggle_breakpoint_under_mouse();
+        }
+        self.fast_forward = self
+            .settings
+            .keymap
+            .actions
+            .iter()
+            .any(|(key, action)| *action == Action::FastForward && self.rl.0.is_key_down(*key));
 
-        keyboard.get_raylib_keys().iter().for_each(|key| {
-            if self.rl.0.is_key_down(*key) {
-                keyboard.press_key(*key);
-            } else {
-                keyboard.release_key(*key);
-            }
-        });
+        // a movie being played back owns the keypad
+        if self.movie.as_ref().is_some_and(|movie| movie.is_playing()) {
+            return;
+        }
+        let mut input =
+            RaylibInput::new(&self.rl.0, &self.settings.keymap.keypad, &mut self.gamepads);
+        self.machine.keyboard().borrow_mut().update(&mut input);
+        if self.movie.is_none() {
+            self.machine.take_key_events();
+        }
     }
 }
+
+// sha-1 of the rom file, the same key the chip-8 community databases use
+pub fn rom_hash(rom: &str) -> std::io::Result<String> {
+    let data = std::fs::read(rom)?;
+    Ok(sha1_smol::Sha1::from(data).digest().to_string())
+}
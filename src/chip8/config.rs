@@ -0,0 +1,148 @@
+use crate::chip8::chip8::{rom_hash, rom_name};
+use crate::chip8::cpu::rng::RngKind;
+use crate::chip8::cpu::timing::Timing;
+use crate::chip8::io::beeper::Waveform;
+use crate::chip8::io::filter::DisplayFilter;
+use crate::chip8::io::keymap::Layout;
+use crate::chip8::io::palette::Palette;
+use crate::chip8::io::scaling::Scaling;
+use crate::chip8::platform::Platform;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// one set of settings, anything left out comes from the layer below, see ChipSettingsBuilder
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettingsLayer {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fg_color: Option<String>,
+    pub bg_color: Option<String>,
+    pub palette: Option<Palette>,
+    pub filter: Option<DisplayFilter>,
+    pub phosphor_frames: Option<u32>,
+    pub scaling: Option<Scaling>,
+    pub pixel_aspect: Option<f32>,
+    pub grid: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub overlay: Option<bool>,
+    pub debugger: Option<bool>,
+    pub fast_forward_speed: Option<f32>,
+    pub slow_motion_speed: Option<f32>,
+    pub cpu_clock: Option<u32>,
+    pub tickrate: Option<u32>,
+    pub vip_clock: Option<u32>,
+    pub timers_rate: Option<u32>,
+    pub platform: Option<Platform>,
+    pub timing: Option<Timing>,
+    pub display_wait: Option<bool>,
+    pub layout: Option<Layout>,
+    pub keymap: Option<String>,
+    pub rng: Option<RngKind>,
+    pub seed: Option<u64>,
+    pub mute: Option<bool>,
+    pub beep_frequency: Option<f32>,
+    pub volume: Option<u8>,
+    pub waveform: Option<Waveform>,
+}
+
+impl SettingsLayer {
+    // values set in other win
+    pub fn merge(&mut self, other: &SettingsLayer) {
+        fn pick<T: Clone>(value: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                *value = other.clone();
+            }
+        }
+        pick(&mut self.width, &other.width);
+        pick(&mut self.height, &other.height);
+        pick(&mut self.fg_color, &other.fg_color);
+        pick(&mut self.bg_color, &other.bg_color);
+        pick(&mut self.palette, &other.palette);
+        pick(&mut self.filter, &other.filter);
+        pick(&mut self.phosphor_frames, &other.phosphor_frames);
+        pick(&mut self.scaling, &other.scaling);
+        pick(&mut self.pixel_aspect, &other.pixel_aspect);
+        pick(&mut self.grid, &other.grid);
+        pick(&mut self.fullscreen, &other.fullscreen);
+        pick(&mut self.overlay, &other.overlay);
+        pick(&mut self.debugger, &other.debugger);
+        pick(&mut self.fast_forward_speed, &other.fast_forward_speed);
+        pick(&mut self.slow_motion_speed, &other.slow_motion_speed);
+        // the two are different ways to say the same thing, the one set last wins
+        if other.cpu_clock.is_some() || other.tickrate.is_some() {
+            self.cpu_clock = other.cpu_clock;
+            self.tickrate = other.tickrate;
+        }
+        pick(&mut self.vip_clock, &other.vip_clock);
+        pick(&mut self.timers_rate, &other.timers_rate);
+        pick(&mut self.platform, &other.platform);
+        pick(&mut self.timing, &other.timing);
+        pick(&mut self.display_wait, &other.display_wait);
+        pick(&mut self.layout, &other.layout);
+        pick(&mut self.keymap, &other.keymap);
+        pick(&mut self.rng, &other.rng);
+        pick(&mut self.seed, &other.seed);
+        pick(&mut self.mute, &other.mute);
+        pick(&mut self.beep_frequency, &other.beep_frequency);
+        pick(&mut self.volume, &other.volume);
+        pick(&mut self.waveform, &other.waveform);
+    }
+}
+
+// the config file, global defaults and per-rom sections keyed by rom file name or sha-1
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // searched for roms besides rom/, see RomLibrary
+    pub rom_dirs: Vec<String>,
+    // programs.json of the chip-8 community database, see RomDatabase
+    pub database: Option<String>,
+    pub defaults: SettingsLayer,
+    pub roms: HashMap<String, SettingsLayer>,
+}
+
+impl Config {
+    // $XDG_CONFIG_HOME/rustychip/config.toml, or ~/.config/rustychip/config.toml
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("rustychip").join("config.toml"))
+    }
+
+    // a missing default config is fine, a missing config that was asked for is not
+    pub fn load(path: Option<&str>) -> Result<Config, String> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => match Config::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+        let config = toml::from_str(&content)
+            .map_err(|err| format!("Invalid config {}: {}", path.display(), err))?;
+        log::info!("Loaded config {}", path.display());
+        Ok(config)
+    }
+
+    pub fn rom_layer(&self, rom: &str) -> Option<&SettingsLayer> {
+        let hash = rom_hash(rom).ok();
+        let layer = self.rom_section(rom, hash.as_deref());
+        if layer.is_some() {
+            log::info!("Using the config section for {}", rom_name(rom));
+        }
+        layer
+    }
+
+    // the hash wins over the name, the same rom can be stored under many names
+    pub fn rom_section(&self, rom: &str, hash: Option<&str>) -> Option<&SettingsLayer> {
+        let by_hash = hash.and_then(|hash| self.roms.get(hash));
+        by_hash.or_else(|| self.roms.get(rom_name(rom)))
+    }
+}
@@ -1,21 +1,85 @@
 use crate::chip8::cpu::instruction::Instruction;
 use crate::chip8::cpu::registers::*;
+use crate::chip8::cpu::rng::Rng;
 use crate::chip8::cpu::stack::Stack;
+use crate::chip8::cpu::timing::vip_cycles;
 use crate::chip8::cpu::timers::Timers;
 use crate::chip8::datatypes::datatypes::*;
 use crate::chip8::io::display::Display;
 use crate::chip8::io::keyboard::Keyboard;
+use crate::chip8::ram::ram::MEMORY_SIZE;
 use crate::chip8::ram::Ram;
-use crate::chip8::util::util::*;
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 const REGISTER_COUNT: usize = 16;
+// how many memory writes the debugger can highlight
+const RECENT_WRITES: usize = 32;
 
+#[derive(Debug, Clone, Copy)]
 enum State {
     Running,
     WaitingForKey(Instruction),
+    // display wait quirk: DRW is done, nothing runs until the next frame
+    WaitingForVblank,
 }
+
+// time spent in each step of an instruction, draw is the execution of DRW and CLS, not showing the result
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Profile {
+    pub fetch: Duration,
+    pub decode: Duration,
+    pub execute: Duration,
+    pub draw: Duration,
+}
+
+// the registers as the debugger shows them
+#[derive(Debug, Clone)]
+pub struct CpuSnapshot {
+    pub v: [u8; REGISTER_COUNT],
+    pub i: u16,
+    pub pc: u16,
+    // return addresses, the oldest call first, sp is its length
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+// everything the cpu itself holds, for save states
+#[derive(Debug, Clone)]
+pub struct CpuState {
+    registers: [Register<VRegisterMarker, Byte>; 16],
+    i: Register<IRegisterMarker, Address>,
+    stack: Stack,
+    pc: Register<PCRegisterMarker, Address>,
+    timers: Timers,
+    rng: Rng,
+    state: State,
+}
+
+impl CpuState {
+    // everything in a fixed order, for checksums that have to match on every machine
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.registers.iter().map(|register| register.value().as_u8()).collect();
+        bytes.extend(self.i.value().as_u16().to_be_bytes());
+        bytes.extend(self.pc.value().as_u16().to_be_bytes());
+        for address in self.stack.entries() {
+            bytes.extend(address.as_u16().to_be_bytes());
+        }
+        bytes.push(self.timers.get_delay_timer());
+        bytes.push(self.timers.get_sound_timer());
+        bytes.extend(self.rng.bytes());
+        bytes.push(match self.state {
+            State::Running => 0,
+            State::WaitingForKey(_) => 1,
+            State::WaitingForVblank => 2,
+        });
+        bytes
+    }
+}
+
 pub struct Cpu {
     registers: [Register<VRegisterMarker, Byte>; 16],
     i: Register<IRegisterMarker, Address>,
@@ -25,7 +89,13 @@ pub struct Cpu {
     display: Rc<RefCell<Display>>,
     ram: Rc<RefCell<Ram>>,
     keyboard: Rc<RefCell<Keyboard>>,
+    rng: Rng,
     state: State,
+    display_wait: bool,
+    // instructions already decoded, by address, cleared wherever memory is written
+    decoded: Vec<Option<Instruction>>,
+    // addresses written to, the newest last, for the debugger
+    recent_writes: VecDeque<Address>,
 }
 
 impl Cpu {
@@ -33,6 +103,8 @@ impl Cpu {
         display: Rc<RefCell<Display>>,
         ram: Rc<RefCell<Ram>>,
         keyboard: Rc<RefCell<Keyboard>>,
+        rng: Rng,
+        display_wait: bool,
     ) -> Cpu {
         let registers = [Register::new(0); REGISTER_COUNT];
         let i = Register::new(0);
@@ -49,21 +121,156 @@ impl Cpu {
             display,
             ram,
             keyboard,
+            rng,
             state: State::Running,
+            display_wait,
+            decoded: vec![None; MEMORY_SIZE],
+            recent_writes: VecDeque::with_capacity(RECENT_WRITES),
         }
     }
 
-    pub fn cycle(&mut self) {
-        match self.state {
+    // returns how many machine cycles the instruction took on the VIP, only VIP timing cares,
+    // see timing.rs
+    pub fn cycle(&mut self) -> u64 {
+        self.step(None)
+    }
+
+    // the same, but measures how long each step took, for the benchmark
+    pub fn cycle_profiled(&mut self, profile: &mut Profile) -> u64 {
+        self.step(Some(profile))
+    }
+
+    fn step(&mut self, mut profile: Option<&mut Profile>) -> u64 {
+        let instruction = match self.state {
             State::Running => {
-                let opcode = self.fetch();
-                let instruction = self.decode(opcode);
-                self.execute(instruction);
+                // only instructions that really run count, not the waiting for a key or the display
+                self.rng.tick();
+                let start = profile.is_some().then(Instant::now);
+                let address = self.pc.value().as_usize();
+                // when the fetch ended and how long decoding took, only on a cache miss
+                let mut decoding = None;
+                let instruction = match self.decoded[address] {
+                    Some(instruction) => {
+                        self.pc.increment();
+                        instruction
+                    }
+                    None => {
+                        let opcode = self.fetch();
+                        let fetched = profile.is_some().then(Instant::now);
+                        let instruction = self.decode(opcode);
+                        decoding = fetched.map(|fetched| (fetched, fetched.elapsed()));
+                        self.decoded[address] = Some(instruction);
+                        instruction
+                    }
+                };
+                // a cache hit is all fetch
+                if let (Some(profile), Some(start)) = (profile.as_deref_mut(), start) {
+                    match decoding {
+                        Some((fetched, decode)) => {
+                            profile.fetch += fetched - start;
+                            profile.decode += decode;
+                        }
+                        None => profile.fetch += start.elapsed(),
+                    }
+                }
+                instruction
             }
-            State::WaitingForKey(instruction) => {
-                self.execute(instruction);
+            State::WaitingForKey(instruction) => instruction,
+            State::WaitingForVblank => return 0,
+        };
+
+        let vx = match instruction {
+            Instruction::DRW(reg, _, _) | Instruction::LDB(reg) => self.registers[reg].value().as_u8(),
+            _ => 0,
+        };
+        let pc = self.pc.value().as_u16();
+        let start = profile.is_some().then(Instant::now);
+        self.execute(instruction);
+        if let (Some(profile), Some(start)) = (profile, start) {
+            match instruction {
+                Instruction::DRW(..) | Instruction::CLS => profile.draw += start.elapsed(),
+                _ => profile.execute += start.elapsed(),
             }
         }
+        let skipped = self.pc.value().as_u16() == pc.wrapping_add(2);
+        vip_cycles(&instruction, vx, skipped)
+    }
+
+    // the scheduler's signal that a new frame has started
+    pub fn vblank(&mut self) {
+        if let State::WaitingForVblank = self.state {
+            self.state = State::Running;
+        }
+    }
+
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        matches!(self.state, State::WaitingForVblank)
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        matches!(self.state, State::WaitingForKey(_))
+    }
+
+    // the next cycle runs a new instruction rather than polling for a key or the display
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, State::Running)
+    }
+
+    pub fn save_state(&self) -> CpuState {
+        CpuState {
+            registers: self.registers,
+            i: self.i,
+            stack: self.stack,
+            pc: self.pc,
+            timers: self.timers,
+            rng: self.rng,
+            state: self.state,
+        }
+    }
+
+    pub fn load_state(&mut self, state: &CpuState) {
+        self.registers = state.registers;
+        self.i = state.i;
+        self.stack = state.stack;
+        self.pc = state.pc;
+        self.timers = state.timers;
+        self.rng = state.rng;
+        self.state = state.state;
+        // memory is restored along with the cpu, it may hold different code now
+        self.decoded.fill(None);
+        self.recent_writes.clear();
+    }
+
+    pub fn snapshot(&self) -> CpuSnapshot {
+        CpuSnapshot {
+            v: self.registers.map(|register| register.value().as_u8()),
+            i: self.i.value().as_u16(),
+            pc: self.pc.value().as_u16(),
+            stack: self.stack.entries().iter().map(|address| address.as_u16()).collect(),
+            delay_timer: self.timers.get_delay_timer(),
+            sound_timer: self.timers.get_sound_timer(),
+        }
+    }
+
+    pub fn pc(&self) -> Address {
+        *self.pc.value()
+    }
+
+    pub fn recent_writes(&self) -> &VecDeque<Address> {
+        &self.recent_writes
+    }
+
+    // what the interpreter looks like right after it started, memory and the rng are left alone
+    pub fn reset(&mut self) {
+        self.registers = [Register::new(0); REGISTER_COUNT];
+        self.i = Register::new(0);
+        self.stack = Stack::new();
+        self.pc.jump(Address(0x200));
+        self.timers = Timers::new();
+        self.state = State::Running;
+        // the rom is loaded again, code it modified is back to the original
+        self.decoded.fill(None);
+        self.recent_writes.clear();
     }
 
     pub fn update_timers(&mut self) {
@@ -80,6 +287,21 @@ impl Cpu {
         word
     }
 
+    // every write to memory goes through here, self-modifying code must not run stale instructions
+    fn store(&mut self, address: Address, value: Byte) {
+        self.ram.borrow_mut().load(address, value);
+        if self.recent_writes.len() == RECENT_WRITES {
+            self.recent_writes.pop_front();
+        }
+        self.recent_writes.push_back(address);
+        // the byte is the second half of the instruction starting one address before
+        let address = address.as_usize();
+        self.decoded[address] = None;
+        if address > 0 {
+            self.decoded[address - 1] = None;
+        }
+    }
+
     fn set_vf(&mut self) {
         self.registers[0xF].load(Byte(1));
     }
@@ -89,100 +311,8 @@ impl Cpu {
     }
 
     fn decode(&mut self, opcode: u16) -> Instruction {
-        match opcode {
-            0x00E0 => Instruction::CLS,
-            0x00EE => Instruction::RET,
-            0x0000..=0x0FFF => Instruction::SYS(Address::new(Address::mask(opcode))),
-            0x1000..=0x1FFF => Instruction::JP(Address::new(Address::mask(opcode))),
-            0x2000..=0x2FFF => Instruction::CALL(Address::new(Address::mask(opcode))),
-            0x3000..=0x3FFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                let val = get_hex_digit_u8(opcode, 2, 2);
-                Instruction::SE(reg, Byte(val))
-            }
-            0x4000..=0x4FFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                let val = get_hex_digit_u8(opcode, 2, 2);
-                Instruction::SNE(reg, Byte(val))
-            }
-            0x5000..=0x5FFF => {
-                let reg1 = get_hex_digit_usize(opcode, 1, 1);
-                let reg2 = get_hex_digit_usize(opcode, 2, 1);
-                Instruction::SEV(reg1, reg2)
-            }
-            0x6000..=0x6FFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                let val = get_hex_digit_u8(opcode, 2, 2);
-                Instruction::LD(reg, Byte(val))
-            }
-            0x7000..=0x7FFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                let val = get_hex_digit_u8(opcode, 2, 2);
-                Instruction::ADD(reg, Byte(val))
-            }
-            0x8000..=0x8FFF => {
-                let reg1 = get_hex_digit_usize(opcode, 1, 1);
-                let reg2 = get_hex_digit_usize(opcode, 2, 1);
-                match get_hex_digit(opcode, 3, 1) {
-                    0x0 => Instruction::LDV(reg1, reg2),
-                    0x1 => Instruction::OR(reg1, reg2),
-                    0x2 => Instruction::AND(reg1, reg2),
-                    0x3 => Instruction::XOR(reg1, reg2),
-                    0x4 => Instruction::ADDV(reg1, reg2),
-                    0x5 => Instruction::SUB(reg1, reg2),
-                    0x6 => Instruction::SHR(reg1, reg2),
-                    0x7 => Instruction::SUBN(reg1, reg2),
-                    0xE => Instruction::SHL(reg1, reg2),
-                    _ => unreachable!("Invalid opcode starting with 8"),
-                }
-            }
-            0x9000..=0x9FFF => {
-                let reg1 = get_hex_digit_usize(opcode, 1, 1);
-                let reg2 = get_hex_digit_usize(opcode, 2, 1);
-                Instruction::SNEV(reg1, reg2)
-            }
-            0xA000..=0xAFFF => Instruction::LDI(Address::new(opcode)),
-            0xB000..=0xBFFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                let addr = Address::new(Address::mask(opcode));
-                Instruction::JPVX(reg, addr)
-            }
-            0xC000..=0xCFFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                let val = get_hex_digit_u8(opcode, 2, 2);
-                Instruction::RND(reg, Byte(val))
-            }
-            0xD000..=0xDFFF => {
-                let reg1 = get_hex_digit_usize(opcode, 1, 1);
-                let reg2 = get_hex_digit_usize(opcode, 2, 1);
-                let nibble = get_hex_digit_u8(opcode, 3, 1);
-                Instruction::DRW(reg1, reg2, Nibble(nibble))
-            }
-            0xE000..=0xEFFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                match get_hex_digit(opcode, 2, 2) {
-                    0x9E => Instruction::SKP(reg),
-                    0xA1 => Instruction::SKNP(reg),
-                    _ => unreachable!("Invalid opcode"),
-                }
-            }
-            0xF000..=0xFFFF => {
-                let reg = get_hex_digit_usize(opcode, 1, 1);
-                match get_hex_digit(opcode, 2, 2) {
-                    0x07 => Instruction::LDDT(reg),
-                    0x0A => Instruction::LDK(reg),
-                    0x15 => Instruction::LDDTV(reg),
-                    0x18 => Instruction::LDST(reg),
-                    0x1E => Instruction::ADDI(reg),
-                    0x29 => Instruction::LDF(reg),
-                    0x33 => Instruction::LDB(reg),
-                    0x55 => Instruction::LDIV(reg),
-                    0x65 => Instruction::LDVI(reg),
-                    _ => unreachable!("Invalid opcode starting with F"),
-                }
-            }
-            _ => unreachable!("Invalid opcode, opcode: {:#X} not matched", opcode),
-        }
+        Instruction::decode(opcode)
+            .unwrap_or_else(|| unreachable!("Invalid opcode, opcode: {:#X} not matched", opcode))
     }
 
     fn execute(&mut self, instruction: Instruction) {
@@ -350,7 +480,7 @@ impl Cpu {
                 self.pc.jump(jmp_addr);
             }
             Instruction::RND(reg, byte) => {
-                let random_byte = rand::random::<u8>();
+                let random_byte = self.rng.next_u8();
                 let value = random_byte & byte.as_u8();
                 self.registers[reg].load(Byte(value));
             }
@@ -375,6 +505,10 @@ impl Cpu {
                 } else {
                     self.unset_vf();
                 }
+
+                if self.display_wait {
+                    self.state = State::WaitingForVblank;
+                }
             }
             Instruction::SKP(reg) => {
                 if self
@@ -430,14 +564,12 @@ impl Cpu {
                 let value = self.registers[reg].value().as_u8();
                 let bcd = [Byte(value / 100), Byte((value / 10) % 10), Byte(value % 10)];
                 for (i, &byte) in bcd.iter().enumerate() {
-                    self.ram.borrow_mut().load(self.i.value() + i, byte);
+                    self.store(self.i.value() + i, byte);
                 }
             }
             Instruction::LDIV(reg) => {
                 for i in 0..=reg {
-                    self.ram
-                        .borrow_mut()
-                        .load(*self.i.value(), *self.registers[i].value());
+                    self.store(*self.i.value(), *self.registers[i].value());
                     self.i.increment();
                 }
             }
@@ -461,3 +593,82 @@ impl Cpu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::cpu::rng::RngKind;
+
+    fn cpu_with(program: &[u8]) -> (Cpu, Rc<RefCell<Ram>>) {
+        let ram = Rc::new(RefCell::new(Ram::new()));
+        for (i, &byte) in program.iter().enumerate() {
+            ram.borrow_mut().load(Address(0x200 + i as u16), Byte(byte));
+        }
+        let cpu = Cpu::new(
+            Rc::new(RefCell::new(Display::new())),
+            ram.clone(),
+            Rc::new(RefCell::new(Keyboard::new())),
+            Rng::new(RngKind::Xorshift, 0),
+            false,
+        );
+        (cpu, ram)
+    }
+
+    fn run(cpu: &mut Cpu, instructions: usize) {
+        for _ in 0..instructions {
+            cpu.cycle();
+        }
+    }
+
+    fn v(cpu: &Cpu, reg: usize) -> u8 {
+        cpu.registers[reg].value().as_u8()
+    }
+
+    #[test]
+    fn code_that_rewrites_itself() {
+        let (mut cpu, _) = cpu_with(&[
+            0x60, 0x05, // LD V0, 0x05
+            0x60, 0x60, // LD V0, 0x60
+            0x61, 0x09, // LD V1, 0x09
+            0xA2, 0x00, // LD I, 0x200
+            0xF1, 0x55, // LD [I], V1, the first instruction becomes LD V0, 0x09
+            0x12, 0x00, // JP 0x200
+        ]);
+        run(&mut cpu, 1);
+        assert_eq!(v(&cpu, 0), 0x05);
+
+        run(&mut cpu, 6);
+        assert_eq!(v(&cpu, 0), 0x09);
+    }
+
+    #[test]
+    fn writing_the_second_byte_of_an_instruction() {
+        let (mut cpu, _) = cpu_with(&[
+            0x60, 0x05, // LD V0, 0x05
+            0x60, 0x09, // LD V0, 0x09
+            0xA2, 0x01, // LD I, 0x201
+            0xF0, 0x55, // LD [I], V0, the first instruction becomes LD V0, 0x09
+            0x60, 0x00, // LD V0, 0x00
+            0x12, 0x00, // JP 0x200
+        ]);
+        run(&mut cpu, 6);
+        assert_eq!(v(&cpu, 0), 0x00);
+
+        run(&mut cpu, 1);
+        assert_eq!(v(&cpu, 0), 0x09);
+    }
+
+    #[test]
+    fn load_state_forgets_decoded_instructions() {
+        let (mut cpu, ram) = cpu_with(&[0x60, 0x05]); // LD V0, 0x05
+        let state = cpu.save_state();
+        run(&mut cpu, 1);
+        assert_eq!(v(&cpu, 0), 0x05);
+
+        // memory comes back from the save state without going through the cpu
+        ram.borrow_mut().load(Address(0x201), Byte(0x09));
+        cpu.load_state(&state);
+        run(&mut cpu, 1);
+        assert_eq!(v(&cpu, 0), 0x09);
+    }
+}
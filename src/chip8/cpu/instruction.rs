@@ -1,5 +1,7 @@
 use crate::chip8::datatypes::datatypes::*;
 use crate::chip8::cpu::registers::*;
+use crate::chip8::util::util::*;
+use std::fmt;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
@@ -50,4 +52,157 @@ pub enum Instruction {
     LDHF(VRegisterNumber),                  // Fx30 - LD HF, Vx
     LDR(VRegisterNumber),                   // Fx75 - LD R, Vx
     LDRV(VRegisterNumber),                  // Fx85 - LD Vx, R
-}
\ No newline at end of file
+}
+
+impl Instruction {
+    // None for anything that isn't an instruction, e.g. sprite data the disassembler runs into
+    pub fn decode(opcode: u16) -> Option<Instruction> {
+        let instruction = match opcode {
+            0x00E0 => Instruction::CLS,
+            0x00EE => Instruction::RET,
+            0x0000..=0x0FFF => Instruction::SYS(Address::new(Address::mask(opcode))),
+            0x1000..=0x1FFF => Instruction::JP(Address::new(Address::mask(opcode))),
+            0x2000..=0x2FFF => Instruction::CALL(Address::new(Address::mask(opcode))),
+            0x3000..=0x3FFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                let val = get_hex_digit_u8(opcode, 2, 2);
+                Instruction::SE(reg, Byte(val))
+            }
+            0x4000..=0x4FFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                let val = get_hex_digit_u8(opcode, 2, 2);
+                Instruction::SNE(reg, Byte(val))
+            }
+            0x5000..=0x5FFF => {
+                let reg1 = get_hex_digit_usize(opcode, 1, 1);
+                let reg2 = get_hex_digit_usize(opcode, 2, 1);
+                Instruction::SEV(reg1, reg2)
+            }
+            0x6000..=0x6FFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                let val = get_hex_digit_u8(opcode, 2, 2);
+                Instruction::LD(reg, Byte(val))
+            }
+            0x7000..=0x7FFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                let val = get_hex_digit_u8(opcode, 2, 2);
+                Instruction::ADD(reg, Byte(val))
+            }
+            0x8000..=0x8FFF => {
+                let reg1 = get_hex_digit_usize(opcode, 1, 1);
+                let reg2 = get_hex_digit_usize(opcode, 2, 1);
+                match get_hex_digit(opcode, 3, 1) {
+                    0x0 => Instruction::LDV(reg1, reg2),
+                    0x1 => Instruction::OR(reg1, reg2),
+                    0x2 => Instruction::AND(reg1, reg2),
+                    0x3 => Instruction::XOR(reg1, reg2),
+                    0x4 => Instruction::ADDV(reg1, reg2),
+                    0x5 => Instruction::SUB(reg1, reg2),
+                    0x6 => Instruction::SHR(reg1, reg2),
+                    0x7 => Instruction::SUBN(reg1, reg2),
+                    0xE => Instruction::SHL(reg1, reg2),
+                    _ => return None,
+                }
+            }
+            0x9000..=0x9FFF => {
+                let reg1 = get_hex_digit_usize(opcode, 1, 1);
+                let reg2 = get_hex_digit_usize(opcode, 2, 1);
+                Instruction::SNEV(reg1, reg2)
+            }
+            0xA000..=0xAFFF => Instruction::LDI(Address::new(opcode)),
+            0xB000..=0xBFFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                let addr = Address::new(Address::mask(opcode));
+                Instruction::JPVX(reg, addr)
+            }
+            0xC000..=0xCFFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                let val = get_hex_digit_u8(opcode, 2, 2);
+                Instruction::RND(reg, Byte(val))
+            }
+            0xD000..=0xDFFF => {
+                let reg1 = get_hex_digit_usize(opcode, 1, 1);
+                let reg2 = get_hex_digit_usize(opcode, 2, 1);
+                let nibble = get_hex_digit_u8(opcode, 3, 1);
+                Instruction::DRW(reg1, reg2, Nibble(nibble))
+            }
+            0xE000..=0xEFFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                match get_hex_digit(opcode, 2, 2) {
+                    0x9E => Instruction::SKP(reg),
+                    0xA1 => Instruction::SKNP(reg),
+                    _ => return None,
+                }
+            }
+            0xF000..=0xFFFF => {
+                let reg = get_hex_digit_usize(opcode, 1, 1);
+                match get_hex_digit(opcode, 2, 2) {
+                    0x07 => Instruction::LDDT(reg),
+                    0x0A => Instruction::LDK(reg),
+                    0x15 => Instruction::LDDTV(reg),
+                    0x18 => Instruction::LDST(reg),
+                    0x1E => Instruction::ADDI(reg),
+                    0x29 => Instruction::LDF(reg),
+                    0x33 => Instruction::LDB(reg),
+                    0x55 => Instruction::LDIV(reg),
+                    0x65 => Instruction::LDVI(reg),
+                    _ => return None,
+                }
+            }
+        };
+        Some(instruction)
+    }
+}
+
+// the mnemonics from the comments above, addresses and bytes in hex
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::SYS(addr) => write!(f, "SYS {:03X}", addr.0),
+            Instruction::CLS => write!(f, "CLS"),
+            Instruction::RET => write!(f, "RET"),
+            Instruction::JP(addr) => write!(f, "JP {:03X}", addr.0),
+            Instruction::CALL(addr) => write!(f, "CALL {:03X}", addr.0),
+            Instruction::SE(x, byte) => write!(f, "SE V{:X}, {:02X}", x, byte.0),
+            Instruction::SNE(x, byte) => write!(f, "SNE V{:X}, {:02X}", x, byte.0),
+            Instruction::SEV(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LD(x, byte) => write!(f, "LD V{:X}, {:02X}", x, byte.0),
+            Instruction::ADD(x, byte) => write!(f, "ADD V{:X}, {:02X}", x, byte.0),
+            Instruction::LDV(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::OR(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::AND(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::XOR(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::ADDV(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::SUB(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::SHR(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SUBN(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::SHL(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SNEV(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LDI(addr) => write!(f, "LD I, {:03X}", addr.0),
+            Instruction::JPVX(_, addr) => write!(f, "JP V0, {:03X}", addr.0),
+            Instruction::RND(x, byte) => write!(f, "RND V{:X}, {:02X}", x, byte.0),
+            Instruction::DRW(x, y, rows) => write!(f, "DRW V{:X}, V{:X}, {:X}", x, y, rows.0),
+            Instruction::SKP(x) => write!(f, "SKP V{:X}", x),
+            Instruction::SKNP(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LDDT(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::LDK(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::LDDTV(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::LDST(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::ADDI(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LDF(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LDB(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::LDIV(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::LDVI(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::SCU(rows) => write!(f, "SCU {:X}", rows.0),
+            Instruction::SCR => write!(f, "SCR"),
+            Instruction::SCL => write!(f, "SCL"),
+            Instruction::EXIT => write!(f, "EXIT"),
+            Instruction::LOW => write!(f, "LOW"),
+            Instruction::HIGH => write!(f, "HIGH"),
+            Instruction::DRW0(x, y) => write!(f, "DRW V{:X}, V{:X}, 0", x, y),
+            Instruction::LDHF(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::LDR(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::LDRV(x) => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
@@ -2,5 +2,7 @@ pub mod cpu;
 pub use cpu::Cpu;
 pub mod instruction;
 pub mod registers;
+pub mod rng;
 pub mod stack;
 pub mod timers;
+pub mod timing;
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RngKind {
+    // xorshift64*, good numbers, the default
+    #[default]
+    Xorshift,
+    // the classic C library linear congruential generator
+    Lcg,
+    // the COSMAC VIP interpreter's own routine, numbers depend on how many instructions ran before
+    Vip,
+}
+
+// the random number generator behind RND, owned by the cpu so it is part of save states
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+    kind: RngKind,
+    state: u64,
+    // vip only: R9 of the interpreter, the low byte counts and picks the byte of VIP_PAGE, the high byte is
+    // the last number
+    r9: u16,
+}
+
+impl Rng {
+    pub fn new(kind: RngKind, seed: u64) -> Rng {
+        // splitmix64 spreads small seeds over the whole state, xorshift must not start at 0
+        let mut state = seed.wrapping_add(0x9e3779b97f4a7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d049bb133111eb);
+        state ^= state >> 31;
+        if state == 0 {
+            state = 1;
+        }
+
+        Rng {
+            kind,
+            state,
+            r9: state as u16,
+        }
+    }
+
+    // for checksums, the kind is a setting and left out
+    pub fn bytes(&self) -> [u8; 10] {
+        let mut bytes = [0; 10];
+        bytes[..8].copy_from_slice(&self.state.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.r9.to_be_bytes());
+        bytes
+    }
+
+    // call once per executed instruction
+    pub fn tick(&mut self) {
+        self.r9 = self.r9.wrapping_add(1);
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        match self.kind {
+            RngKind::Xorshift => {
+                self.state ^= self.state >> 12;
+                self.state ^= self.state << 25;
+                self.state ^= self.state >> 27;
+                (self.state.wrapping_mul(0x2545f4914f6cdd1d) >> 56) as u8
+            }
+            RngKind::Lcg => {
+                self.state = self.state.wrapping_mul(1103515245).wrapping_add(12345) & 0x7fffffff;
+                (self.state >> 16) as u8
+            }
+            RngKind::Vip => {
+                // the interpreter's CXKK routine at 0x1D9: INC R9, add the byte of page 1 at R9.0 to R9.1,
+                // then add that sum shifted right with the carry in bit 7 to itself. The mask is up to the caller
+                self.r9 = self.r9.wrapping_add(1);
+                let [high, low] = self.r9.to_be_bytes();
+                let (sum, carry) = high.overflowing_add(VIP_PAGE[low as usize]);
+                let shifted = (sum >> 1) | ((carry as u8) << 7);
+                let number = shifted.wrapping_add(sum);
+                self.r9 = u16::from_be_bytes([number, low]);
+                number
+            }
+        }
+    }
+}
+
+// the second page (0x100 - 0x1FF) of the VIP's chip-8 interpreter, RND reads its random bytes from here
+const VIP_PAGE: [u8; 256] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x45, 0xA3, 0x98, 0x56, 0xD4, 0xF8, 0x81, 0xBC, 0xF8, 0x95, 0xAC,
+    0x22, 0xDC, 0x12, 0x56, 0xD4, 0x06, 0xB8, 0xD4, 0x06, 0xA8, 0xD4, 0x64, 0x0A, 0x01, 0xE6, 0x8A,
+    0xF4, 0xAA, 0x3B, 0x28, 0x9A, 0xFC, 0x01, 0xBA, 0xD4, 0xF8, 0x81, 0xBA, 0x06, 0xFA, 0x0F, 0xAA,
+    0x0A, 0xAA, 0xD4, 0xE6, 0x06, 0xBF, 0x93, 0xBE, 0xF8, 0x1B, 0xAE, 0x2A, 0x1A, 0xF8, 0x00, 0x5A,
+    0x0E, 0xF5, 0x3B, 0x4B, 0x56, 0x0A, 0xFC, 0x01, 0x5A, 0x30, 0x40, 0x4E, 0xF6, 0x3B, 0x3C, 0x9F,
+    0x56, 0x2A, 0x2A, 0xD4, 0x00, 0x22, 0x86, 0x52, 0xF8, 0xF0, 0xA7, 0x07, 0x5A, 0x87, 0xF3, 0x17,
+    0x1A, 0x3A, 0x5B, 0x12, 0xD4, 0x22, 0x86, 0x52, 0xF8, 0xF0, 0xA7, 0x0A, 0x57, 0x87, 0xF3, 0x17,
+    0x1A, 0x3A, 0x6B, 0x12, 0xD4, 0x15, 0x85, 0x22, 0x73, 0x95, 0x52, 0x25, 0x45, 0xA5, 0x86, 0xFA,
+    0x0F, 0xB5, 0xD4, 0x45, 0xE6, 0xF3, 0x3A, 0x82, 0x15, 0x15, 0xD4, 0x45, 0xE6, 0xF3, 0x3A, 0x88,
+    0xD4, 0x45, 0x07, 0x30, 0x8C, 0x45, 0x07, 0x30, 0x84, 0xE6, 0x62, 0x26, 0x45, 0xA3, 0x36, 0x88,
+    0xD4, 0x3E, 0x88, 0xD4, 0xF8, 0xF0, 0xA7, 0xE7, 0x45, 0xF4, 0xA5, 0x86, 0xFA, 0x0F, 0x3B, 0xB2,
+    0xFC, 0x01, 0xB5, 0xD4, 0x45, 0x56, 0xD4, 0x45, 0xE6, 0xF4, 0x56, 0xD4, 0x45, 0xFA, 0x0F, 0x3A,
+    0xC4, 0x07, 0x56, 0xD4, 0xAF, 0x22, 0xF8, 0xD3, 0x73, 0x8F, 0xF9, 0xF0, 0x52, 0xE6, 0x07, 0xD2,
+    0x56, 0xF8, 0xFF, 0xA6, 0xF8, 0x00, 0x7E, 0x56, 0xD4, 0x19, 0x89, 0xAE, 0x93, 0xBE, 0x99, 0xEE,
+    0xF4, 0x56, 0x76, 0xE6, 0xF4, 0xB9, 0x56, 0x45, 0xF2, 0x56, 0xD4, 0x45, 0xAA, 0x86, 0xFA, 0x0F,
+    0xBA, 0xD4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, 0x00, 0x4B,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(rng: &mut Rng, count: usize) -> Vec<u8> {
+        (0..count).map(|_| rng.next_u8()).collect()
+    }
+
+    fn vip(r9: u16) -> Rng {
+        Rng {
+            kind: RngKind::Vip,
+            state: 0,
+            r9,
+        }
+    }
+
+    #[test]
+    fn same_seed_same_numbers() {
+        for kind in [RngKind::Xorshift, RngKind::Lcg, RngKind::Vip] {
+            assert_eq!(numbers(&mut Rng::new(kind, 7), 32), numbers(&mut Rng::new(kind, 7), 32));
+        }
+        assert_ne!(
+            numbers(&mut Rng::new(RngKind::Xorshift, 7), 32),
+            numbers(&mut Rng::new(RngKind::Xorshift, 8), 32)
+        );
+    }
+
+    #[test]
+    fn xorshift_sequence() {
+        assert_eq!(numbers(&mut Rng::new(RngKind::Xorshift, 0), 4), vec![0x7B, 0xDE, 0xB3, 0xE0]);
+        assert_eq!(numbers(&mut Rng::new(RngKind::Xorshift, 42), 4), vec![0x31, 0x90, 0x7C, 0x45]);
+    }
+
+    #[test]
+    fn lcg_sequence() {
+        assert_eq!(numbers(&mut Rng::new(RngKind::Lcg, 0), 4), vec![0xB6, 0x8E, 0x96, 0x83]);
+    }
+
+    #[test]
+    fn vip_adds_the_page_byte() {
+        // R9 0x0004 becomes 0x0005, 0x00 + 0x45 = 0x45, 0x45 + 0x22 = 0x67
+        assert_eq!(VIP_PAGE[0x05], 0x45);
+        let mut rng = vip(0x0004);
+        assert_eq!(numbers(&mut rng, 4), vec![0x67, 0x8F, 0xBA, 0x98]);
+        assert_eq!(rng.r9, 0x9808);
+    }
+
+    #[test]
+    fn vip_shifts_the_carry_in() {
+        // 0xF0 + 0xDC = 0x1CC, 0xCC + (0x66 | 0x80) = 0xB2
+        assert_eq!(VIP_PAGE[0x11], 0xDC);
+        assert_eq!(vip(0xF010).next_u8(), 0xB2);
+    }
+
+    #[test]
+    fn vip_depends_on_the_instructions_run() {
+        let mut rng = vip(0x0004);
+        rng.next_u8();
+        rng.tick();
+        assert_eq!(rng.next_u8(), 0x7E);
+    }
+}
@@ -3,6 +3,7 @@ use crate::chip8::datatypes::datatypes::*;
 
 const STACK_SIZE: usize = 16;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Stack {
     stack: [Address; STACK_SIZE],
 }
@@ -19,6 +20,11 @@ impl Stack {
         self.stack[index] = address;
     }
 
+    // return addresses, the oldest call first
+    pub fn entries(&self) -> Vec<Address> {
+        self.stack.iter().take_while(|&&x| x != Address(0)).copied().collect()
+    }
+
     pub fn pop(&mut self) -> Option<Address> {
         let index = self.stack.iter().rposition(|&x| x != Address(0))?;
         let address = self.stack[index];
@@ -1,4 +1,5 @@
 
+#[derive(Debug, Clone, Copy)]
 pub struct Timers {
     delay_timer: u8,
     sound_timer: u8,
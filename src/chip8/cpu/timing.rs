@@ -0,0 +1,85 @@
+use crate::chip8::cpu::instruction::Instruction;
+use serde::{Deserialize, Serialize};
+
+// the VIP's 1802 runs at 1.76064 MHz, every machine cycle takes 8 clock cycles
+pub const VIP_CLOCK: u64 = 1_760_640;
+pub const CLOCKS_PER_MACHINE_CYCLE: u64 = 8;
+// the 1861 display steals 128 lines of 8 bytes by dma every frame, plus the interrupt routine
+// that sets it up and counts the timers down
+pub const INTERRUPT_CYCLES: u64 = 128 * 8 + 46;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Timing {
+    // a fixed number of instructions per frame, whatever they are
+    #[default]
+    Instructions,
+    // every instruction takes as long as it did in the VIP interpreter, --cpu-clock is the 1802 clock
+    Vip,
+}
+
+// approximate costs of the interpreter's routines in 1802 machine cycles, fetch and decode included,
+// vx is the value of the instruction's x register before it ran and skipped whether it skipped
+// the next instruction
+pub fn vip_cycles(instruction: &Instruction, vx: u8, skipped: bool) -> u64 {
+    // a taken skip increments the program counter once more
+    let skip = if skipped { 2 } else { 0 };
+    match *instruction {
+        Instruction::SYS(_) => 40,
+        // the whole display buffer is zeroed byte by byte
+        Instruction::CLS => 24 + 256 * 3,
+        Instruction::RET => 23,
+        Instruction::JP(_) => 23,
+        Instruction::CALL(_) => 23,
+        Instruction::SE(..) | Instruction::SNE(..) => 12 + skip,
+        Instruction::SEV(..) | Instruction::SNEV(..) => 14 + skip,
+        Instruction::LD(..) => 6,
+        Instruction::ADD(..) => 10,
+        // the 8xyn routines are built in ram and run as 1802 code
+        Instruction::LDV(..)
+        | Instruction::OR(..)
+        | Instruction::AND(..)
+        | Instruction::XOR(..)
+        | Instruction::ADDV(..)
+        | Instruction::SUB(..)
+        | Instruction::SHR(..)
+        | Instruction::SUBN(..)
+        | Instruction::SHL(..) => 44,
+        Instruction::LDI(_) => 12,
+        Instruction::JPVX(..) => 22,
+        Instruction::RND(..) => 36,
+        // with the display wait quirk the rest of the frame is spent waiting first, see Machine
+        Instruction::DRW(_, _, rows) => draw_cycles(vx, rows.0 as u64),
+        Instruction::SKP(_) | Instruction::SKNP(_) => 14 + skip,
+        Instruction::LDDT(_) | Instruction::LDDTV(_) | Instruction::LDST(_) => 10,
+        // per check of the keypad, the interpreter keeps coming back until a key is pressed
+        Instruction::LDK(_) => 10,
+        Instruction::ADDI(_) => 16,
+        Instruction::LDF(_) => 20,
+        // repeated subtraction, one round per unit of every digit
+        Instruction::LDB(_) => {
+            let digits = (vx / 100 + vx / 10 % 10 + vx % 10) as u64;
+            84 + 16 * digits
+        }
+        Instruction::LDIV(x) | Instruction::LDVI(x) => 14 + 14 * (x as u64 + 1),
+        // not on the VIP
+        Instruction::SCU(_)
+        | Instruction::SCR
+        | Instruction::SCL
+        | Instruction::EXIT
+        | Instruction::LOW
+        | Instruction::HIGH
+        | Instruction::DRW0(..)
+        | Instruction::LDHF(_)
+        | Instruction::LDR(_)
+        | Instruction::LDRV(_) => 0,
+    }
+}
+
+// a sprite row lands on one display byte when x is a multiple of 8, otherwise it is shifted
+// into two bytes one bit at a time
+fn draw_cycles(x: u8, rows: u64) -> u64 {
+    let shift = (x % 8) as u64;
+    let per_row = if shift == 0 { 22 } else { 34 + 4 * shift };
+    26 + rows * per_row
+}
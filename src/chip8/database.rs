@@ -0,0 +1,77 @@
+use crate::chip8::platform::Platform;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// a program in the chip-8 community database's programs.json, only the parts we show
+#[derive(Debug, Deserialize)]
+struct Program {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    authors: Option<Vec<String>>,
+    // releases of the program, by sha-1
+    #[serde(default)]
+    roms: HashMap<String, RomRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RomRelease {
+    #[serde(default)]
+    platforms: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RomInfo {
+    pub title: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    // the database's platform ids, e.g. "originalChip8" or "superchip", best first
+    pub platforms: Vec<String>,
+}
+
+impl RomInfo {
+    // the first of the rom's platforms that can be emulated
+    pub fn platform(&self) -> Option<Platform> {
+        self.platforms.iter().find_map(|id| match id.as_str() {
+            "originalChip8" => Some(Platform::Vip),
+            "modernChip8" => Some(Platform::Chip8),
+            _ => None,
+        })
+    }
+}
+
+// keyed by the sha-1 of the rom, see rom_hash
+#[derive(Debug, Default)]
+pub struct RomDatabase {
+    roms: HashMap<String, RomInfo>,
+}
+
+impl RomDatabase {
+    pub fn load(path: &str) -> Result<RomDatabase, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read ROM database {}: {}", path, err))?;
+        let programs: Vec<Program> = serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid ROM database {}: {}", path, err))?;
+
+        let mut roms = HashMap::new();
+        for program in programs {
+            for (hash, release) in program.roms {
+                let info = RomInfo {
+                    title: program.title.clone(),
+                    description: program.description.clone(),
+                    authors: program.authors.clone().unwrap_or_default(),
+                    platforms: release.platforms,
+                };
+                roms.insert(hash.to_lowercase(), info);
+            }
+        }
+        log::info!("Loaded {} ROMs from {}", roms.len(), path);
+        Ok(RomDatabase { roms })
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&RomInfo> {
+        self.roms.get(hash)
+    }
+}
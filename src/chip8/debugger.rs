@@ -0,0 +1,91 @@
+use crate::chip8::cpu::cpu::CpuSnapshot;
+use crate::chip8::cpu::instruction::Instruction;
+use crate::chip8::datatypes::datatypes::Address;
+use crate::chip8::machine::Machine;
+use crate::chip8::ram::ram::MEMORY_SIZE;
+
+// bytes per row of the memory view
+pub const MEMORY_ROW_LENGTH: usize = 8;
+// the tallest sprite DRW draws
+pub const SPRITE_ROWS: usize = 15;
+// disassembly lines and memory rows shown before pc and I, the rest come after them
+const LINES_BEFORE_PC: usize = 3;
+const ROWS_BEFORE_I: usize = 1;
+
+pub struct DisassemblyLine {
+    pub address: u16,
+    pub opcode: u16,
+    // the mnemonic, or the opcode as data if it isn't an instruction
+    pub text: String,
+    pub breakpoint: bool,
+}
+
+pub struct MemoryRow {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
+
+// everything the debugger panels show, read from the machine in one go so frontends only draw it
+pub struct DebugView {
+    pub cpu: CpuSnapshot,
+    // lines around pc, in steps of two bytes from pc so they line up with the running code
+    pub disassembly: Vec<DisassemblyLine>,
+    // rows around I
+    pub memory: Vec<MemoryRow>,
+    // addresses written to lately, the newest last
+    pub recent_writes: Vec<u16>,
+    // the bytes at I, one sprite row each
+    pub sprite: Vec<u8>,
+}
+
+impl DebugView {
+    pub fn new(machine: &Machine, disassembly_lines: usize, memory_rows: usize) -> DebugView {
+        let cpu = machine.cpu();
+        let ram = machine.ram();
+        let read = |address: usize| ram.read(Address(address as u16)).0;
+
+        let pc = cpu.pc as usize;
+        let first = pc - (2 * LINES_BEFORE_PC).min(pc / 2 * 2);
+        let disassembly = (first..MEMORY_SIZE - 1)
+            .step_by(2)
+            .take(disassembly_lines)
+            .map(|address| {
+                let opcode = (read(address) as u16) << 8 | read(address + 1) as u16;
+                let text = match Instruction::decode(opcode) {
+                    Some(instruction) => instruction.to_string(),
+                    None => format!("DW {:04X}", opcode),
+                };
+                DisassemblyLine {
+                    address: address as u16,
+                    opcode,
+                    text,
+                    breakpoint: machine.breakpoints().contains(&(address as u16)),
+                }
+            })
+            .collect();
+
+        // rows stay aligned, I is somewhere in the second one
+        let i = cpu.i as usize;
+        let last_row = MEMORY_SIZE / MEMORY_ROW_LENGTH - memory_rows.min(MEMORY_SIZE / MEMORY_ROW_LENGTH);
+        let first_row = (i / MEMORY_ROW_LENGTH).saturating_sub(ROWS_BEFORE_I).min(last_row);
+        let memory = (first_row..first_row + memory_rows)
+            .map(|row| {
+                let address = row * MEMORY_ROW_LENGTH;
+                MemoryRow {
+                    address: address as u16,
+                    bytes: (address..address + MEMORY_ROW_LENGTH).map(read).collect(),
+                }
+            })
+            .collect();
+
+        let sprite = (i..(i + SPRITE_ROWS).min(MEMORY_SIZE)).map(read).collect();
+
+        DebugView {
+            recent_writes: machine.recent_writes(),
+            cpu,
+            disassembly,
+            memory,
+            sprite,
+        }
+    }
+}
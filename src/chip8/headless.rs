@@ -0,0 +1,103 @@
+use crate::chip8::machine::Machine;
+use crate::chip8::record::{GifRecorder, Movie, WavRecorder};
+use crate::chip8::ChipSettings;
+
+// runs the machine without a window, as fast as possible, frame by frame
+pub struct Headless {
+    machine: Machine,
+    settings: ChipSettings,
+    gif: Option<GifRecorder>,
+    wav: Option<WavRecorder>,
+    movie: Option<Movie>,
+}
+
+impl Headless {
+    pub fn new(settings: ChipSettings) -> Headless {
+        let machine = Machine::new(&settings);
+
+        Headless {
+            machine,
+            settings,
+            gif: None,
+            wav: None,
+            movie: None,
+        }
+    }
+
+    pub fn start_gif_recording(&mut self, path: &str) {
+        match GifRecorder::new(path, self.settings.colors) {
+            Ok(recorder) => self.gif = Some(recorder),
+            Err(err) => log::error!("Failed to start GIF recording to {}: {}", path, err),
+        }
+    }
+
+    pub fn start_wav_recording(&mut self, path: &str, sample_rate: u32) {
+        match WavRecorder::new(
+            path,
+            self.settings.beeper,
+            sample_rate,
+            self.settings.timers_rate,
+        ) {
+            Ok(recorder) => self.wav = Some(recorder),
+            Err(err) => log::error!("Failed to start WAV recording to {}: {}", path, err),
+        }
+    }
+
+    // records the (untouched) keypad, or plays a movie back into it
+    pub fn start_movie(&mut self, movie: Movie) {
+        self.movie = Some(movie);
+    }
+
+    pub fn run(&mut self, frames: u64) {
+        for _ in 0..frames {
+            self.run_frame();
+        }
+
+        if let Some(movie) = self.movie.take() {
+            if let Err(err) = movie.finish(&self.machine) {
+                log::error!("{}", err);
+            }
+        }
+
+        if let Some(recorder) = self.gif.take() {
+            let path = recorder.path().to_string();
+            match recorder.finish() {
+                Ok(()) => log::info!("GIF saved to {}", path),
+                Err(err) => log::error!("Failed to save GIF {}: {}", path, err),
+            }
+        }
+        if let Some(recorder) = self.wav.take() {
+            let path = recorder.path().to_string();
+            match recorder.finish() {
+                Ok(()) => log::info!("WAV saved to {}", path),
+                Err(err) => log::error!("Failed to save WAV {}: {}", path, err),
+            }
+        }
+    }
+
+    fn run_frame(&mut self) {
+        if let Some(movie) = &mut self.movie {
+            movie.before_frame(&self.machine);
+        }
+
+        for beep in self.machine.run_frame() {
+            if let Some(recorder) = &mut self.wav {
+                if let Err(err) = recorder.tick(beep) {
+                    log::error!("WAV recording failed: {}", err);
+                    self.wav = None;
+                }
+            }
+        }
+
+        if let Some(movie) = &mut self.movie {
+            movie.after_frame(&self.machine);
+        }
+
+        if let Some(recorder) = &mut self.gif {
+            if let Err(err) = recorder.capture(&self.machine.display()) {
+                log::error!("GIF recording failed: {}", err);
+                self.gif = None;
+            }
+        }
+    }
+}
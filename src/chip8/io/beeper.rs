@@ -0,0 +1,95 @@
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+// length of the fade in/out when the tone starts or stops, a hard cut is heard as a click
+const RAMP_SECONDS: f32 = 0.005;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeeperSettings {
+    pub frequency: f32,
+    pub volume: f32,
+    pub waveform: Waveform,
+    pub muted: bool,
+}
+
+impl Default for BeeperSettings {
+    fn default() -> BeeperSettings {
+        BeeperSettings {
+            frequency: 440.0,
+            volume: 0.25,
+            waveform: Waveform::Square,
+            muted: false,
+        }
+    }
+}
+
+// generates the buzzer tone sample by sample, independent of any audio device
+#[derive(Debug, Clone)]
+pub struct Beeper {
+    settings: BeeperSettings,
+    sample_rate: u32,
+    // position within the current period, 0.0..1.0
+    phase: f32,
+    // envelope, 0.0 = silent, 1.0 = full volume
+    gain: f32,
+}
+
+impl Beeper {
+    pub fn new(settings: BeeperSettings, sample_rate: u32) -> Beeper {
+        Beeper {
+            settings,
+            sample_rate,
+            phase: 0.0,
+            gain: 0.0,
+        }
+    }
+
+    pub fn fill(&mut self, buffer: &mut [f32], on: bool) {
+        let target = if on && !self.settings.muted { 1.0 } else { 0.0 };
+        let ramp_step = 1.0 / (RAMP_SECONDS * self.sample_rate as f32);
+        let phase_step = self.settings.frequency / self.sample_rate as f32;
+
+        for sample in buffer.iter_mut() {
+            if self.gain < target {
+                self.gain = (self.gain + ramp_step).min(target);
+            } else if self.gain > target {
+                self.gain = (self.gain - ramp_step).max(target);
+            }
+
+            if self.gain == 0.0 {
+                // restart the period so every beep begins the same way
+                self.phase = 0.0;
+                *sample = 0.0;
+                continue;
+            }
+
+            *sample = self.wave(self.phase) * self.settings.volume * self.gain;
+            self.phase = (self.phase + phase_step).fract();
+        }
+    }
+
+    fn wave(&self, phase: f32) -> f32 {
+        match self.settings.waveform {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
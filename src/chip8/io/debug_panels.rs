@@ -0,0 +1,173 @@
+use crate::chip8::debugger::{DebugView, SPRITE_ROWS};
+use raylib::prelude::{Color, RaylibDraw};
+
+// the panels take this much of the window's right side, the game gets the rest
+pub const PANELS_WIDTH: i32 = 2 * COLUMN_WIDTH + PADDING;
+const COLUMN_WIDTH: i32 = 220;
+const FONT_SIZE: i32 = 10;
+const LINE_HEIGHT: i32 = 12;
+const PADDING: i32 = 8;
+// width of one byte in the memory view, the default font isn't monospaced so bytes are placed one by one
+const BYTE_WIDTH: i32 = 18;
+const SPRITE_PIXEL: i32 = 8;
+const MEMORY_ROWS: usize = 8;
+
+const BACKGROUND: Color = Color::new(24, 24, 24, 255);
+const TITLE: Color = Color::GRAY;
+const TEXT: Color = Color::LIGHTGRAY;
+const CURRENT: Color = Color::new(60, 60, 90, 255);
+const BREAKPOINT: Color = Color::RED;
+const I_HIGHLIGHT: Color = Color::new(90, 80, 20, 255);
+const WRITTEN: Color = Color::ORANGE;
+const PIXEL_OFF: Color = Color::new(50, 50, 50, 255);
+
+// registers, stack and sprite on the left, disassembly and memory on the right,
+// everything drawn from a DebugView
+pub struct DebugPanels {
+    // left, top and height of the panels area
+    x: i32,
+    y: i32,
+    height: i32,
+}
+
+impl DebugPanels {
+    pub fn new(window: (i32, i32)) -> DebugPanels {
+        DebugPanels {
+            x: window.0 - PANELS_WIDTH,
+            y: 0,
+            height: window.1,
+        }
+    }
+
+    // as many as fit between the disassembly title and the memory panel
+    pub fn disassembly_lines(&self) -> usize {
+        let memory_height = 2 * PADDING + LINE_HEIGHT * (MEMORY_ROWS as i32 + 1);
+        ((self.height - memory_height - PADDING - LINE_HEIGHT) / LINE_HEIGHT).max(1) as usize
+    }
+
+    pub fn memory_rows(&self) -> usize {
+        MEMORY_ROWS
+    }
+
+    fn right_column(&self) -> i32 {
+        self.x + PADDING + COLUMN_WIDTH
+    }
+
+    fn disassembly_top(&self) -> i32 {
+        self.y + PADDING + LINE_HEIGHT
+    }
+
+    // the address of the disassembly line under the mouse, clicking it toggles a breakpoint
+    pub fn line_at(&self, view: &DebugView, mouse: (f32, f32)) -> Option<u16> {
+        let (x, y) = (mouse.0 as i32, mouse.1 as i32);
+        if x < self.right_column() || y < self.disassembly_top() {
+            return None;
+        }
+        let line = ((y - self.disassembly_top()) / LINE_HEIGHT) as usize;
+        view.disassembly.get(line).map(|line| line.address)
+    }
+
+    pub fn draw(&self, handle: &mut impl RaylibDraw, view: &DebugView) {
+        handle.draw_rectangle(self.x, self.y, PANELS_WIDTH, self.height, BACKGROUND);
+        let left = self.x + PADDING;
+        let mut y = self.y + PADDING;
+        y = self.draw_registers(handle, view, left, y);
+        y = self.draw_stack(handle, view, left, y + PADDING);
+        self.draw_sprite(handle, view, left, y + PADDING);
+
+        let y = self.draw_disassembly(handle, view, self.right_column(), self.y + PADDING);
+        self.draw_memory(handle, view, self.right_column(), y + PADDING);
+    }
+
+    fn draw_registers(&self, handle: &mut impl RaylibDraw, view: &DebugView, x: i32, mut y: i32) -> i32 {
+        let cpu = &view.cpu;
+        handle.draw_text("Registers", x, y, FONT_SIZE, TITLE);
+        y += LINE_HEIGHT;
+        for (row, values) in cpu.v.chunks(4).enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                let register = format!("V{:X} {:02X}", row * 4 + column, value);
+                handle.draw_text(&register, x + column as i32 * 50, y, FONT_SIZE, TEXT);
+            }
+            y += LINE_HEIGHT;
+        }
+        let lines = [
+            format!("I  {:03X}    PC {:03X}", cpu.i, cpu.pc),
+            format!("DT {:02X}     ST {:02X}     SP {}", cpu.delay_timer, cpu.sound_timer, cpu.stack.len()),
+        ];
+        for line in lines.iter() {
+            handle.draw_text(line, x, y, FONT_SIZE, TEXT);
+            y += LINE_HEIGHT;
+        }
+        y
+    }
+
+    fn draw_stack(&self, handle: &mut impl RaylibDraw, view: &DebugView, x: i32, mut y: i32) -> i32 {
+        handle.draw_text("Stack", x, y, FONT_SIZE, TITLE);
+        y += LINE_HEIGHT;
+        if view.cpu.stack.is_empty() {
+            handle.draw_text("empty", x, y, FONT_SIZE, TEXT);
+            return y + LINE_HEIGHT;
+        }
+        // the innermost call first
+        for row in view.cpu.stack.iter().rev().collect::<Vec<_>>().chunks(4) {
+            for (column, address) in row.iter().enumerate() {
+                handle.draw_text(&format!("{:03X}", address), x + column as i32 * 50, y, FONT_SIZE, TEXT);
+            }
+            y += LINE_HEIGHT;
+        }
+        y
+    }
+
+    fn draw_sprite(&self, handle: &mut impl RaylibDraw, view: &DebugView, x: i32, mut y: i32) {
+        handle.draw_text(&format!("Sprite at I, 8x{}", SPRITE_ROWS), x, y, FONT_SIZE, TITLE);
+        y += LINE_HEIGHT;
+        for (row, byte) in view.sprite.iter().enumerate() {
+            for bit in 0..8 {
+                let lit = byte & (0x80 >> bit) != 0;
+                let color = if lit { TEXT } else { PIXEL_OFF };
+                handle.draw_rectangle(
+                    x + bit * SPRITE_PIXEL,
+                    y + row as i32 * SPRITE_PIXEL,
+                    SPRITE_PIXEL - 1,
+                    SPRITE_PIXEL - 1,
+                    color,
+                );
+            }
+        }
+    }
+
+    fn draw_disassembly(&self, handle: &mut impl RaylibDraw, view: &DebugView, x: i32, mut y: i32) -> i32 {
+        handle.draw_text("Disassembly, click to toggle breakpoints", x, y, FONT_SIZE, TITLE);
+        y += LINE_HEIGHT;
+        for line in view.disassembly.iter() {
+            if line.address == view.cpu.pc {
+                handle.draw_rectangle(x - 2, y - 1, COLUMN_WIDTH - PADDING, LINE_HEIGHT, CURRENT);
+            }
+            if line.breakpoint {
+                handle.draw_circle(x + 3, y + FONT_SIZE / 2, 3.0, BREAKPOINT);
+            }
+            let text = format!("{:03X}  {:04X}  {}", line.address, line.opcode, line.text);
+            handle.draw_text(&text, x + 10, y, FONT_SIZE, TEXT);
+            y += LINE_HEIGHT;
+        }
+        y
+    }
+
+    fn draw_memory(&self, handle: &mut impl RaylibDraw, view: &DebugView, x: i32, mut y: i32) {
+        handle.draw_text("Memory at I, recent writes in orange", x, y, FONT_SIZE, TITLE);
+        y += LINE_HEIGHT;
+        for row in view.memory.iter() {
+            handle.draw_text(&format!("{:03X}", row.address), x, y, FONT_SIZE, TITLE);
+            for (column, byte) in row.bytes.iter().enumerate() {
+                let address = row.address + column as u16;
+                let byte_x = x + 30 + column as i32 * BYTE_WIDTH;
+                if address == view.cpu.i {
+                    handle.draw_rectangle(byte_x - 2, y - 1, BYTE_WIDTH - 2, LINE_HEIGHT, I_HIGHLIGHT);
+                }
+                let color = if view.recent_writes.contains(&address) { WRITTEN } else { TEXT };
+                handle.draw_text(&format!("{:02X}", byte), byte_x, y, FONT_SIZE, color);
+            }
+            y += LINE_HEIGHT;
+        }
+    }
+}
@@ -1,14 +1,32 @@
 
 
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+
+// the storage is sized for the 128x64 hires mode, only the top left DISPLAY_WIDTH x DISPLAY_HEIGHT is used
+const MAX_WIDTH: usize = 128;
+const MAX_HEIGHT: usize = 64;
+
+// one bit per pixel, column 0 is the highest bit of the row
+type Row = u128;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Display {
-    display: [u8; 64 * 32],
+    rows: [Row; MAX_HEIGHT],
+    // one bit per row changed since the frontend last looked, see take_dirty_rows
+    dirty_rows: u64,
 }
 
+// every row of the display
+const ALL_ROWS: u64 = u64::MAX >> (MAX_HEIGHT - DISPLAY_HEIGHT);
+// the columns of a row that are on screen
+const VISIBLE_COLUMNS: Row = Row::MAX << (MAX_WIDTH - DISPLAY_WIDTH);
+
 impl Display {
     pub fn new() -> Display {
         Display {
-            display: [0; 64 * 32],
+            rows: [0; MAX_HEIGHT],
+            dirty_rows: ALL_ROWS,
         }
     }
 }
@@ -21,48 +39,146 @@ impl Default for Display {
 
 impl Display {
     pub fn clear(&mut self) {
-        self.display = [0; 64 * 32];
+        self.rows = [0; MAX_HEIGHT];
+        self.dirty_rows = ALL_ROWS;
     }
 
-    // pub fn set_pixel(&mut self, x: usize, y: usize) {
-    //     self.display[x + y * 64] = 1;
-    // }
+    // bit n is set if row n changed since the last call
+    pub fn take_dirty_rows(&mut self) -> u64 {
+        std::mem::take(&mut self.dirty_rows)
+    }
 
-    // pub fn unset_pixel(&mut self, x: usize, y: usize) {
-    //     self.display[x + y * 64] = 0;
-    // }
+    // e.g. after a save state was loaded, the frontend can't know what changed
+    pub fn mark_dirty(&mut self) {
+        self.dirty_rows = ALL_ROWS;
+    }
 
     pub fn get_pixel(&self, x: usize, y: usize) -> u8 {
-        self.display[x + y * 64]
+        ((self.rows[y] >> (MAX_WIDTH - 1 - x)) & 1) as u8
+    }
+
+    // columns and rows, what the window scales to fit
+    pub fn size(&self) -> (usize, usize) {
+        (DISPLAY_WIDTH, DISPLAY_HEIGHT)
     }
 
-    pub fn get_display(&self) -> &[u8; 64 * 32] {
-        &self.display
+    // one byte per pixel, row by row
+    pub fn get_display(&self) -> [u8; DISPLAY_WIDTH * DISPLAY_HEIGHT] {
+        let mut pixels = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        for (y, row) in pixels.chunks_exact_mut(DISPLAY_WIDTH).enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.get_pixel(x, y);
+            }
+        }
+        pixels
     }
 
+    // a whole sprite row at once: shifted into place, clipped at the right edge, then XORed in,
+    // any bit set in both the row and the sprite is a collision
     pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> u8 {
-        let mut collision = 0;
-        let x = x % 64;
-        let y = y % 32;
+        let mut collision = false;
+        let x = x % DISPLAY_WIDTH;
+        let y = y % DISPLAY_HEIGHT;
         for (j, &sprite_byte) in sprite.iter().enumerate() {
-            for i in 0..8 {
-                let sprite_pixel = (sprite_byte >> (7 - i)) & 1;
-                let x = x + i;
-                let y = y + j;
-                if x >= 64 || y >= 32 {
-                    continue;
-                }
-                let pixel = self.get_pixel(x, y);
-                collision |= pixel & sprite_pixel;
-                self.display[x + y * 64] ^= sprite_pixel;
+            let y = y + j;
+            if y >= DISPLAY_HEIGHT {
+                break;
             }
+            let bits = ((sprite_byte as Row) << (MAX_WIDTH - 8) >> x) & VISIBLE_COLUMNS;
+            if bits == 0 {
+                continue;
+            }
+            collision |= self.rows[y] & bits != 0;
+            self.rows[y] ^= bits;
+            self.dirty_rows |= 1 << y;
         }
 
-        collision
+        collision as u8
     }
 
     pub fn get_display_as_bytes(&self) -> Vec<u8> {
-        self.display.to_vec()
+        self.get_display().to_vec()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // columns lit in a row, left to right
+    fn lit(display: &Display, y: usize) -> Vec<usize> {
+        (0..DISPLAY_WIDTH).filter(|&x| display.get_pixel(x, y) == 1).collect()
     }
 
+    #[test]
+    fn draws_at_the_edges_of_a_row() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &[0xFF]);
+        assert_eq!(lit(&display, 0), (0..8).collect::<Vec<_>>());
+
+        display.draw_sprite(56, 1, &[0xFF]);
+        assert_eq!(lit(&display, 1), (56..64).collect::<Vec<_>>());
+
+        display.draw_sprite(63, 2, &[0b1000_0001]);
+        assert_eq!(lit(&display, 2), vec![63]);
+    }
+
+    #[test]
+    fn the_start_wraps_the_sprite_clips() {
+        let mut display = Display::new();
+        display.draw_sprite(64 + 3, 32 + 1, &[0x80]);
+        assert_eq!(lit(&display, 1), vec![3]);
+
+        // nothing comes back in at the left or at the top
+        display.draw_sprite(60, 30, &[0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(lit(&display, 30), vec![60, 61, 62, 63]);
+        assert_eq!(lit(&display, 31), vec![60, 61, 62, 63]);
+        assert!(lit(&display, 0).is_empty());
+        assert_eq!(lit(&display, 1), vec![3]);
+    }
+
+    #[test]
+    fn collisions() {
+        let mut display = Display::new();
+        assert_eq!(display.draw_sprite(10, 10, &[0xF0]), 0);
+        assert_eq!(display.draw_sprite(14, 10, &[0xF0]), 0);
+        // erases the first sprite
+        assert_eq!(display.draw_sprite(10, 10, &[0xF0]), 1);
+        assert_eq!(lit(&display, 10), vec![14, 15, 16, 17]);
+        // the clipped part doesn't collide
+        display.draw_sprite(60, 0, &[0x0F]);
+        assert_eq!(display.draw_sprite(56, 0, &[0xF0]), 0);
+    }
+
+    #[test]
+    fn one_byte_per_pixel() {
+        let mut display = Display::new();
+        display.draw_sprite(5, 2, &[0x80]);
+        display.draw_sprite(63, 31, &[0x80]);
+
+        let pixels = display.get_display();
+        assert_eq!(pixels.iter().filter(|&&pixel| pixel == 1).count(), 2);
+        assert_eq!(pixels[2 * DISPLAY_WIDTH + 5], 1);
+        assert_eq!(pixels[DISPLAY_WIDTH * DISPLAY_HEIGHT - 1], 1);
+        assert_eq!(display.get_display_as_bytes(), pixels.to_vec());
+    }
+
+    #[test]
+    fn dirty_rows() {
+        let mut display = Display::new();
+        assert_eq!(display.take_dirty_rows(), ALL_ROWS);
+        assert_eq!(display.take_dirty_rows(), 0);
+
+        display.draw_sprite(0, 3, &[0x80, 0x00, 0x80]);
+        // the empty sprite row doesn't change anything
+        assert_eq!(display.take_dirty_rows(), (1 << 3) | (1 << 5));
+
+        // all of it off screen
+        display.draw_sprite(60, 0, &[0x08]);
+        assert_eq!(display.take_dirty_rows(), 0);
+
+        display.clear();
+        assert_eq!(display.take_dirty_rows(), ALL_ROWS);
+    }
 }
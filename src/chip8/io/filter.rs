@@ -0,0 +1,222 @@
+use crate::chip8::io::display::{Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::chip8::io::palette::Colors;
+use serde::Deserialize;
+
+const PIXELS: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayFilter {
+    // the display as it is whenever it is shown
+    #[default]
+    None,
+    // only what the display looks like at the end of each 60 Hz frame. the frontends only present whole frames,
+    // so this is what None shows as well
+    Vblank,
+    // pixels that go dark fade out over a few frames, like a CRT
+    Phosphor,
+    // the average of the last two frames
+    Blend,
+}
+
+// turns the display into RGBA pixels, hiding the flicker of sprites being erased and redrawn
+#[derive(Debug, Clone)]
+pub struct ScreenFilter {
+    filter: DisplayFilter,
+    colors: Colors,
+    // phosphor only: how many frames a pixel takes to fade out
+    decay_frames: u32,
+    current: [u8; PIXELS],
+    previous: [u8; PIXELS],
+    // phosphor only: last lit value of every pixel and frames since it went dark
+    lit: [u8; PIXELS],
+    age: [u32; PIXELS],
+    rgba: Vec<u8>,
+}
+
+impl ScreenFilter {
+    pub fn new(filter: DisplayFilter, colors: Colors, decay_frames: u32) -> ScreenFilter {
+        let mut screen = ScreenFilter {
+            filter,
+            colors,
+            decay_frames: decay_frames.max(1),
+            current: [0; PIXELS],
+            previous: [0; PIXELS],
+            lit: [0; PIXELS],
+            age: [u32::MAX; PIXELS],
+            rgba: vec![0; PIXELS * 4],
+        };
+        screen.render();
+        screen
+    }
+
+    // call once per 60 Hz frame with the rows that changed since the last call, returns whether
+    // the picture changed, if not the frontend doesn't need to draw it again
+    pub fn present(&mut self, display: &Display, dirty_rows: u64) -> bool {
+        match self.filter {
+            DisplayFilter::None | DisplayFilter::Vblank => {
+                if dirty_rows == 0 {
+                    return false;
+                }
+                self.current = display.get_display();
+                let mut changed = false;
+                for row in (0..DISPLAY_HEIGHT).filter(|row| dirty_rows & (1 << row) != 0) {
+                    changed |= self.render_row(row);
+                }
+                changed
+            }
+            // these change from frame to frame on their own, every row has to be looked at
+            DisplayFilter::Phosphor | DisplayFilter::Blend => {
+                self.previous = self.current;
+                self.current = display.get_display();
+                if self.filter == DisplayFilter::Phosphor {
+                    for i in 0..PIXELS {
+                        if self.current[i] != 0 {
+                            self.lit[i] = self.current[i];
+                            self.age[i] = 0;
+                        } else {
+                            self.age[i] = self.age[i].saturating_add(1);
+                        }
+                    }
+                }
+                let mut changed = false;
+                for row in 0..DISPLAY_HEIGHT {
+                    changed |= self.render_row(row);
+                }
+                changed
+            }
+        }
+    }
+
+    // 4 bytes per pixel, row by row
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    fn render(&mut self) {
+        for row in 0..DISPLAY_HEIGHT {
+            self.render_row(row);
+        }
+    }
+
+    // returns whether any pixel of the row changed colour
+    fn render_row(&mut self, row: usize) -> bool {
+        let mut changed = false;
+        for i in row * DISPLAY_WIDTH..(row + 1) * DISPLAY_WIDTH {
+            let color = match self.filter {
+                DisplayFilter::None | DisplayFilter::Vblank => self.colors[self.current[i] as usize],
+                DisplayFilter::Phosphor => {
+                    if self.current[i] != 0 || self.age[i] >= self.decay_frames {
+                        self.colors[self.current[i] as usize]
+                    } else {
+                        let lit = self.colors[self.lit[i] as usize];
+                        let fade = self.age[i] as f32 / self.decay_frames as f32;
+                        mix(lit, self.colors[0], fade)
+                    }
+                }
+                DisplayFilter::Blend => mix(
+                    self.colors[self.previous[i] as usize],
+                    self.colors[self.current[i] as usize],
+                    0.5,
+                ),
+            };
+            let rgba = [(color >> 16) as u8, (color >> 8) as u8, color as u8, 255];
+            if self.rgba[i * 4..i * 4 + 4] != rgba {
+                self.rgba[i * 4..i * 4 + 4].copy_from_slice(&rgba);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+// t = 0.0 is all a, 1.0 is all b
+fn mix(a: u32, b: u32, t: f32) -> u32 {
+    let channel = |shift: u32| {
+        let a = ((a >> shift) & 0xFF) as f32;
+        let b = ((b >> shift) & 0xFF) as f32;
+        ((a + (b - a) * t).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLORS: Colors = [0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00];
+
+    // the top left pixel, the only one the tests draw
+    fn first_pixel(screen: &ScreenFilter) -> [u8; 4] {
+        screen.rgba()[0..4].try_into().unwrap()
+    }
+
+    fn toggle_first_pixel(display: &mut Display) -> u64 {
+        display.draw_sprite(0, 0, &[0x80]);
+        display.take_dirty_rows()
+    }
+
+    #[test]
+    fn none_shows_the_display_as_it_is() {
+        for filter in [DisplayFilter::None, DisplayFilter::Vblank] {
+            let mut display = Display::new();
+            let mut screen = ScreenFilter::new(filter, COLORS, 5);
+            assert_eq!(first_pixel(&screen), [0, 0, 0, 255]);
+
+            let dirty_rows = toggle_first_pixel(&mut display);
+            assert!(screen.present(&display, dirty_rows));
+            assert_eq!(first_pixel(&screen), [255, 255, 255, 255]);
+            // nothing was drawn since
+            let dirty_rows = display.take_dirty_rows();
+            assert!(!screen.present(&display, dirty_rows));
+
+            let dirty_rows = toggle_first_pixel(&mut display);
+            assert!(screen.present(&display, dirty_rows));
+            assert_eq!(first_pixel(&screen), [0, 0, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn phosphor_fades_dark_pixels_out() {
+        let mut display = Display::new();
+        let mut screen = ScreenFilter::new(DisplayFilter::Phosphor, COLORS, 4);
+        let dirty_rows = toggle_first_pixel(&mut display);
+        screen.present(&display, dirty_rows);
+        assert_eq!(first_pixel(&screen), [255, 255, 255, 255]);
+
+        // a quarter darker every frame, black after decay_frames
+        let dirty_rows = toggle_first_pixel(&mut display);
+        let mut fade = Vec::new();
+        assert!(screen.present(&display, dirty_rows));
+        fade.push(first_pixel(&screen)[0]);
+        for _ in 0..3 {
+            assert!(screen.present(&display, 0));
+            fade.push(first_pixel(&screen)[0]);
+        }
+        assert_eq!(fade, vec![191, 128, 64, 0]);
+        assert!(!screen.present(&display, 0));
+
+        // lit again right away
+        let dirty_rows = toggle_first_pixel(&mut display);
+        screen.present(&display, dirty_rows);
+        assert_eq!(first_pixel(&screen), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn blend_averages_two_frames() {
+        let mut display = Display::new();
+        let mut screen = ScreenFilter::new(DisplayFilter::Blend, COLORS, 5);
+        let dirty_rows = toggle_first_pixel(&mut display);
+        assert!(screen.present(&display, dirty_rows));
+        assert_eq!(first_pixel(&screen), [128, 128, 128, 255]);
+        assert!(screen.present(&display, 0));
+        assert_eq!(first_pixel(&screen), [255, 255, 255, 255]);
+        assert!(!screen.present(&display, 0));
+
+        let dirty_rows = toggle_first_pixel(&mut display);
+        screen.present(&display, dirty_rows);
+        assert_eq!(first_pixel(&screen), [128, 128, 128, 255]);
+        // the rest of the screen stays the background
+        assert_eq!(screen.rgba()[4..8], [0, 0, 0, 255]);
+    }
+}
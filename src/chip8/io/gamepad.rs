@@ -0,0 +1,254 @@
+use crate::chip8::io::keyboard::Key;
+use raylib::consts::{GamepadAxis, GamepadButton};
+use std::collections::HashMap;
+
+// raylib (glfw) supports up to 4 gamepads
+const MAX_GAMEPADS: i32 = 4;
+// how far a stick has to be pushed to count as a press
+const AXIS_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadInput {
+    Button(GamepadButton),
+    // true = positive direction (right / down), false = negative (left / up)
+    Axis(GamepadAxis, bool),
+}
+
+pub type GamepadBindings = HashMap<GamepadInput, Key>;
+
+// where gamepad state comes from, raylib in the window, anything else in tests
+pub trait GamepadSource {
+    fn is_gamepad_available(&self, gamepad: i32) -> bool;
+    fn is_gamepad_button_down(&self, gamepad: i32, button: GamepadButton) -> bool;
+    fn get_gamepad_axis_movement(&self, gamepad: i32, axis: GamepadAxis) -> f32;
+}
+
+impl GamepadSource for raylib::RaylibHandle {
+    fn is_gamepad_available(&self, gamepad: i32) -> bool {
+        raylib::RaylibHandle::is_gamepad_available(self, gamepad)
+    }
+
+    fn is_gamepad_button_down(&self, gamepad: i32, button: GamepadButton) -> bool {
+        raylib::RaylibHandle::is_gamepad_button_down(self, gamepad, button)
+    }
+
+    fn get_gamepad_axis_movement(&self, gamepad: i32, axis: GamepadAxis) -> f32 {
+        raylib::RaylibHandle::get_gamepad_axis_movement(self, gamepad, axis)
+    }
+}
+
+// d-pad and left stick on the keys under W A S D, face buttons on Q and E
+pub fn default_gamepad_bindings() -> GamepadBindings {
+    use GamepadAxis::*;
+    use GamepadButton::*;
+    HashMap::from([
+        (GamepadInput::Button(GAMEPAD_BUTTON_LEFT_FACE_UP), Key::Key5),
+        (
+            GamepadInput::Button(GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+            Key::Key7,
+        ),
+        (
+            GamepadInput::Button(GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+            Key::Key8,
+        ),
+        (
+            GamepadInput::Button(GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+            Key::Key9,
+        ),
+        (GamepadInput::Axis(GAMEPAD_AXIS_LEFT_Y, false), Key::Key5),
+        (GamepadInput::Axis(GAMEPAD_AXIS_LEFT_X, false), Key::Key7),
+        (GamepadInput::Axis(GAMEPAD_AXIS_LEFT_Y, true), Key::Key8),
+        (GamepadInput::Axis(GAMEPAD_AXIS_LEFT_X, true), Key::Key9),
+        (
+            GamepadInput::Button(GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+            Key::Key6,
+        ),
+        (
+            GamepadInput::Button(GAMEPAD_BUTTON_RIGHT_FACE_RIGHT),
+            Key::Key4,
+        ),
+    ])
+}
+
+#[derive(Debug, Clone)]
+pub struct Gamepads {
+    bindings: GamepadBindings,
+    connected: [bool; MAX_GAMEPADS as usize],
+}
+
+impl Gamepads {
+    pub fn new(bindings: GamepadBindings) -> Gamepads {
+        Gamepads {
+            bindings,
+            connected: [false; MAX_GAMEPADS as usize],
+        }
+    }
+
+    // chip-8 keys held on any connected gamepad, gamepads can come and go at any time
+    pub fn poll(&mut self, source: &impl GamepadSource) -> [bool; 16] {
+        let mut keys = [false; 16];
+        for gamepad in 0..MAX_GAMEPADS {
+            let available = source.is_gamepad_available(gamepad);
+            if available != self.connected[gamepad as usize] {
+                if available {
+                    log::info!("Gamepad {} connected", gamepad);
+                } else {
+                    log::info!("Gamepad {} disconnected", gamepad);
+                }
+                self.connected[gamepad as usize] = available;
+            }
+            if !available {
+                continue;
+            }
+
+            for (input, key) in self.bindings.iter() {
+                let held = match *input {
+                    GamepadInput::Button(button) => source.is_gamepad_button_down(gamepad, button),
+                    GamepadInput::Axis(axis, true) => {
+                        source.get_gamepad_axis_movement(gamepad, axis) > AXIS_THRESHOLD
+                    }
+                    GamepadInput::Axis(axis, false) => {
+                        source.get_gamepad_axis_movement(gamepad, axis) < -AXIS_THRESHOLD
+                    }
+                };
+                if held {
+                    keys[*key as usize] = true;
+                }
+            }
+        }
+        keys
+    }
+}
+
+// raylib names without the GAMEPAD_BUTTON_ prefix, axes as e.g. "LEFT_X-" or "LEFT_Y+",
+// plus the usual names: DPAD_UP, A, B, X, Y (xbox positions), START, SELECT, LB, RB, LT, RT
+pub fn parse_gamepad_input(name: &str) -> Result<GamepadInput, String> {
+    let upper = name.trim().to_uppercase();
+
+    if let Some(axis) = upper.strip_suffix('+').or(upper.strip_suffix('-')) {
+        let positive = upper.ends_with('+');
+        let axis = match axis.strip_prefix("GAMEPAD_AXIS_").unwrap_or(axis) {
+            "LEFT_X" => GamepadAxis::GAMEPAD_AXIS_LEFT_X,
+            "LEFT_Y" => GamepadAxis::GAMEPAD_AXIS_LEFT_Y,
+            "RIGHT_X" => GamepadAxis::GAMEPAD_AXIS_RIGHT_X,
+            "RIGHT_Y" => GamepadAxis::GAMEPAD_AXIS_RIGHT_Y,
+            "LEFT_TRIGGER" => GamepadAxis::GAMEPAD_AXIS_LEFT_TRIGGER,
+            "RIGHT_TRIGGER" => GamepadAxis::GAMEPAD_AXIS_RIGHT_TRIGGER,
+            _ => return Err(format!("Unknown gamepad axis '{}'", name)),
+        };
+        return Ok(GamepadInput::Axis(axis, positive));
+    }
+
+    let button = match upper.strip_prefix("GAMEPAD_BUTTON_").unwrap_or(&upper) {
+        "LEFT_FACE_UP" | "DPAD_UP" => GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP,
+        "LEFT_FACE_RIGHT" | "DPAD_RIGHT" => GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT,
+        "LEFT_FACE_DOWN" | "DPAD_DOWN" => GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN,
+        "LEFT_FACE_LEFT" | "DPAD_LEFT" => GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT,
+        "RIGHT_FACE_UP" | "Y" => GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP,
+        "RIGHT_FACE_RIGHT" | "B" => GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT,
+        "RIGHT_FACE_DOWN" | "A" => GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN,
+        "RIGHT_FACE_LEFT" | "X" => GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT,
+        "LEFT_TRIGGER_1" | "LB" => GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1,
+        "LEFT_TRIGGER_2" | "LT" => GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2,
+        "RIGHT_TRIGGER_1" | "RB" => GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1,
+        "RIGHT_TRIGGER_2" | "RT" => GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2,
+        "MIDDLE_LEFT" | "SELECT" | "BACK" => GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT,
+        "MIDDLE" | "GUIDE" => GamepadButton::GAMEPAD_BUTTON_MIDDLE,
+        "MIDDLE_RIGHT" | "START" => GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT,
+        "LEFT_THUMB" => GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB,
+        "RIGHT_THUMB" => GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB,
+        _ => return Err(format!("Unknown gamepad button '{}'", name)),
+    };
+    Ok(GamepadInput::Button(button))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use GamepadAxis::*;
+    use GamepadButton::*;
+
+    // gamepads plugged in, buttons held and stick positions, by gamepad number
+    #[derive(Default)]
+    struct FakeSource {
+        available: [bool; MAX_GAMEPADS as usize],
+        buttons: Vec<(i32, GamepadButton)>,
+        axes: Vec<(i32, GamepadAxis, f32)>,
+    }
+
+    impl GamepadSource for FakeSource {
+        fn is_gamepad_available(&self, gamepad: i32) -> bool {
+            self.available[gamepad as usize]
+        }
+
+        fn is_gamepad_button_down(&self, gamepad: i32, button: GamepadButton) -> bool {
+            self.buttons.contains(&(gamepad, button))
+        }
+
+        fn get_gamepad_axis_movement(&self, gamepad: i32, axis: GamepadAxis) -> f32 {
+            self.axes
+                .iter()
+                .find(|(pad, stick, _)| *pad == gamepad && *stick == axis)
+                .map_or(0.0, |(_, _, movement)| *movement)
+        }
+    }
+
+    fn held(keys: [bool; 16]) -> Vec<Key> {
+        (0..16u8).filter(|key| keys[*key as usize]).filter_map(Key::from_u8).collect()
+    }
+
+    #[test]
+    fn buttons_map_to_their_keys() {
+        let mut gamepads = Gamepads::new(default_gamepad_bindings());
+        let mut source = FakeSource {
+            available: [true, false, false, false],
+            buttons: vec![(0, GAMEPAD_BUTTON_LEFT_FACE_UP), (0, GAMEPAD_BUTTON_RIGHT_FACE_DOWN)],
+            ..FakeSource::default()
+        };
+        assert_eq!(held(gamepads.poll(&source)), vec![Key::Key5, Key::Key6]);
+
+        // not bound to anything
+        source.buttons = vec![(0, GAMEPAD_BUTTON_MIDDLE_RIGHT)];
+        assert_eq!(held(gamepads.poll(&source)), vec![]);
+    }
+
+    #[test]
+    fn sticks_count_past_the_threshold() {
+        let mut gamepads = Gamepads::new(default_gamepad_bindings());
+        let mut source = FakeSource {
+            available: [true, false, false, false],
+            ..FakeSource::default()
+        };
+
+        source.axes = vec![(0, GAMEPAD_AXIS_LEFT_X, AXIS_THRESHOLD)];
+        assert_eq!(held(gamepads.poll(&source)), vec![]);
+        source.axes = vec![(0, GAMEPAD_AXIS_LEFT_X, 0.8)];
+        assert_eq!(held(gamepads.poll(&source)), vec![Key::Key9]);
+        source.axes = vec![(0, GAMEPAD_AXIS_LEFT_X, -0.8)];
+        assert_eq!(held(gamepads.poll(&source)), vec![Key::Key7]);
+        source.axes = vec![(0, GAMEPAD_AXIS_LEFT_Y, -1.0), (0, GAMEPAD_AXIS_LEFT_X, 0.3)];
+        assert_eq!(held(gamepads.poll(&source)), vec![Key::Key5]);
+    }
+
+    #[test]
+    fn gamepads_come_and_go() {
+        let mut gamepads = Gamepads::new(default_gamepad_bindings());
+        let mut source = FakeSource {
+            buttons: vec![(2, GAMEPAD_BUTTON_LEFT_FACE_DOWN)],
+            ..FakeSource::default()
+        };
+
+        // a button of a gamepad that isn't there doesn't count
+        assert_eq!(held(gamepads.poll(&source)), vec![]);
+        assert_eq!(gamepads.connected, [false; 4]);
+
+        source.available[2] = true;
+        assert_eq!(held(gamepads.poll(&source)), vec![Key::Key8]);
+        assert_eq!(gamepads.connected, [false, false, true, false]);
+
+        // pulled out while the button was held, the key is released
+        source.available[2] = false;
+        assert_eq!(held(gamepads.poll(&source)), vec![]);
+        assert_eq!(gamepads.connected, [false; 4]);
+    }
+}
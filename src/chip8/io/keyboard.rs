@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::VecDeque;
 
-pub type Keybindings = HashMap<raylib::consts::KeyboardKey, Key>;
+// how many press / release edges are kept until someone collects them
+const EVENT_HISTORY: usize = 64;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Key {
@@ -22,100 +23,170 @@ pub enum Key {
     KeyF,
 }
 
+impl Key {
+    pub fn from_u8(value: u8) -> Option<Key> {
+        match value {
+            0x0 => Some(Key::Key0),
+            0x1 => Some(Key::Key1),
+            0x2 => Some(Key::Key2),
+            0x3 => Some(Key::Key3),
+            0x4 => Some(Key::Key4),
+            0x5 => Some(Key::Key5),
+            0x6 => Some(Key::Key6),
+            0x7 => Some(Key::Key7),
+            0x8 => Some(Key::Key8),
+            0x9 => Some(Key::Key9),
+            0xA => Some(Key::KeyA),
+            0xB => Some(Key::KeyB),
+            0xC => Some(Key::KeyC),
+            0xD => Some(Key::KeyD),
+            0xE => Some(Key::KeyE),
+            0xF => Some(Key::KeyF),
+            _ => None,
+        }
+    }
+}
+
+// a key going down or coming back up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub pressed: bool,
+}
+
+// anything that can hold chip-8 keys down: the window, a terminal, a script, the network
+pub trait InputSource {
+    // marks the keys this source holds down right now, keys it doesn't know about are left alone
+    fn poll(&mut self, held: &mut [bool; 16]);
+}
+
+// the hex keypad as the chip-8 program sees it, knows nothing about where key presses come from
 #[derive(Debug, Clone)]
 pub struct Keyboard {
-    keybindings: Keybindings,
     keys: [bool; 16],
+    events: VecDeque<KeyEvent>,
 }
 
 impl Keyboard {
-    pub fn new(swap_yz: bool) -> Keyboard {
-        let mut keybindings = HashMap::new();
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_ONE, Key::Key1);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_TWO, Key::Key2);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_THREE, Key::Key3);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_FOUR, Key::KeyC);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_Q, Key::Key4);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_W, Key::Key5);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_E, Key::Key6);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_R, Key::KeyD);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_A, Key::Key7);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_S, Key::Key8);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_D, Key::Key9);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_F, Key::KeyE);
-        if swap_yz {
-            keybindings.insert(raylib::consts::KeyboardKey::KEY_Y, Key::KeyA);
-        } else {
-            keybindings.insert(raylib::consts::KeyboardKey::KEY_Z, Key::KeyA);
-        }
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_X, Key::Key0);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_C, Key::KeyB);
-        keybindings.insert(raylib::consts::KeyboardKey::KEY_V, Key::KeyF);
-
+    pub fn new() -> Keyboard {
         Keyboard {
-            keybindings,
             keys: [false; 16],
+            events: VecDeque::with_capacity(EVENT_HISTORY),
         }
     }
+}
 
-    pub fn new_with_keybindings(keybindings: Keybindings) -> Keyboard {
-        Keyboard {
-            keybindings,
-            keys: [false; 16],
-        }
+impl Default for Keyboard {
+    fn default() -> Keyboard {
+        Keyboard::new()
     }
+}
 
+impl Keyboard {
     pub fn is_pressed(&self, key: u8) -> bool {
         self.keys[key as usize]
     }
 
-    pub fn press_key(&mut self, key: raylib::consts::KeyboardKey) {
-        if let Some(chip8_key) = self.keybindings.get(&key) {
-            match chip8_key {
-                Key::Key0 => self.keys[0] = true,
-                Key::Key1 => self.keys[1] = true,
-                Key::Key2 => self.keys[2] = true,
-                Key::Key3 => self.keys[3] = true,
-                Key::Key4 => self.keys[4] = true,
-                Key::Key5 => self.keys[5] = true,
-                Key::Key6 => self.keys[6] = true,
-                Key::Key7 => self.keys[7] = true,
-                Key::Key8 => self.keys[8] = true,
-                Key::Key9 => self.keys[9] = true,
-                Key::KeyA => self.keys[10] = true,
-                Key::KeyB => self.keys[11] = true,
-                Key::KeyC => self.keys[12] = true,
-                Key::KeyD => self.keys[13] = true,
-                Key::KeyE => self.keys[14] = true,
-                Key::KeyF => self.keys[15] = true,
+    pub fn set_key(&mut self, key: Key, pressed: bool) {
+        if self.keys[key as usize] == pressed {
+            return;
+        }
+        self.keys[key as usize] = pressed;
+
+        if self.events.len() == EVENT_HISTORY {
+            self.events.pop_front();
+        }
+        self.events.push_back(KeyEvent { key, pressed });
+    }
+
+    // sets every key to what the source reports, a key not held by the source is released
+    pub fn update(&mut self, source: &mut impl InputSource) {
+        let mut held = [false; 16];
+        source.poll(&mut held);
+        for (i, pressed) in held.into_iter().enumerate() {
+            if let Some(key) = Key::from_u8(i as u8) {
+                self.set_key(key, pressed);
             }
         }
     }
 
-    pub fn release_key(&mut self, key: raylib::consts::KeyboardKey) {
-        if let Some(chip8_key) = self.keybindings.get(&key) {
-            match chip8_key {
-                Key::Key0 => self.keys[0] = false,
-                Key::Key1 => self.keys[1] = false,
-                Key::Key2 => self.keys[2] = false,
-                Key::Key3 => self.keys[3] = false,
-                Key::Key4 => self.keys[4] = false,
-                Key::Key5 => self.keys[5] = false,
-                Key::Key6 => self.keys[6] = false,
-                Key::Key7 => self.keys[7] = false,
-                Key::Key8 => self.keys[8] = false,
-                Key::Key9 => self.keys[9] = false,
-                Key::KeyA => self.keys[10] = false,
-                Key::KeyB => self.keys[11] = false,
-                Key::KeyC => self.keys[12] = false,
-                Key::KeyD => self.keys[13] = false,
-                Key::KeyE => self.keys[14] = false,
-                Key::KeyF => self.keys[15] = false,
+    // press and release edges since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<KeyEvent> {
+        self.events.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Holding(Vec<Key>);
+
+    impl InputSource for Holding {
+        fn poll(&mut self, held: &mut [bool; 16]) {
+            for key in &self.0 {
+                held[*key as usize] = true;
             }
         }
     }
 
-    pub fn get_raylib_keys(&self) -> Vec<raylib::consts::KeyboardKey> {
-        self.keybindings.keys().cloned().collect()
+    fn press(key: Key) -> KeyEvent {
+        KeyEvent { key, pressed: true }
+    }
+
+    fn release(key: Key) -> KeyEvent {
+        KeyEvent { key, pressed: false }
+    }
+
+    #[test]
+    fn only_changes_are_events() {
+        let mut keyboard = Keyboard::new();
+        keyboard.set_key(Key::Key5, true);
+        keyboard.set_key(Key::Key5, true);
+        keyboard.set_key(Key::KeyA, false);
+        keyboard.set_key(Key::Key5, false);
+
+        assert!(!keyboard.is_pressed(0x5));
+        assert_eq!(keyboard.take_events(), vec![press(Key::Key5), release(Key::Key5)]);
+    }
+
+    #[test]
+    fn take_events_empties_the_queue() {
+        let mut keyboard = Keyboard::new();
+        keyboard.set_key(Key::KeyF, true);
+        assert_eq!(keyboard.take_events(), vec![press(Key::KeyF)]);
+        assert_eq!(keyboard.take_events(), vec![]);
+
+        // the key is still held, taking the events doesn't release it
+        assert!(keyboard.is_pressed(0xF));
+    }
+
+    #[test]
+    fn only_the_latest_events_are_kept() {
+        let mut keyboard = Keyboard::new();
+        for _ in 0..EVENT_HISTORY {
+            keyboard.set_key(Key::Key1, true);
+            keyboard.set_key(Key::Key1, false);
+        }
+
+        let events = keyboard.take_events();
+        assert_eq!(events.len(), EVENT_HISTORY);
+        // the first half of the presses and releases fell out
+        assert_eq!(events[0], press(Key::Key1));
+        assert_eq!(events[EVENT_HISTORY - 1], release(Key::Key1));
+    }
+
+    #[test]
+    fn update_releases_keys_the_source_lets_go() {
+        let mut keyboard = Keyboard::new();
+        keyboard.update(&mut Holding(vec![Key::Key2, Key::KeyC]));
+        assert!(keyboard.is_pressed(0x2) && keyboard.is_pressed(0xC));
+
+        keyboard.update(&mut Holding(vec![Key::KeyC]));
+        assert!(!keyboard.is_pressed(0x2) && keyboard.is_pressed(0xC));
+        assert_eq!(
+            keyboard.take_events(),
+            vec![press(Key::Key2), press(Key::KeyC), release(Key::Key2)]
+        );
     }
 }
@@ -0,0 +1,389 @@
+use crate::chip8::io::gamepad::{
+    default_gamepad_bindings, parse_gamepad_input, GamepadBindings,
+};
+use crate::chip8::io::keyboard::Key;
+use raylib::consts::KeyboardKey;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub type Keybindings = HashMap<KeyboardKey, Key>;
+pub type ActionBindings = HashMap<KeyboardKey, Action>;
+
+// the COSMAC VIP hex keypad, row by row
+pub const KEYPAD: [[Key; 4]; 4] = [
+    [Key::Key1, Key::Key2, Key::Key3, Key::KeyC],
+    [Key::Key4, Key::Key5, Key::Key6, Key::KeyD],
+    [Key::Key7, Key::Key8, Key::Key9, Key::KeyE],
+    [Key::KeyA, Key::Key0, Key::KeyB, Key::KeyF],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Qwerty,
+    Qwertz,
+    Azerty,
+    Dvorak,
+    Colemak,
+    Numpad,
+}
+
+impl Layout {
+    // raylib reports keys by their position on a US keyboard, whatever the layout prints on them, so the keypad is
+    // the same 4x4 block at the left side of every keyboard. the layouts only differ in what that block types
+    fn keys(&self) -> [[KeyboardKey; 4]; 4] {
+        use KeyboardKey::*;
+        match self {
+            // 7 8 9 on top like the keypad's 1 2 3, the operators make up the right column
+            Layout::Numpad => [
+                [KEY_KP_7, KEY_KP_8, KEY_KP_9, KEY_KP_DIVIDE],
+                [KEY_KP_4, KEY_KP_5, KEY_KP_6, KEY_KP_MULTIPLY],
+                [KEY_KP_1, KEY_KP_2, KEY_KP_3, KEY_KP_SUBTRACT],
+                [KEY_KP_0, KEY_KP_DECIMAL, KEY_KP_ENTER, KEY_KP_ADD],
+            ],
+            _ => [
+                [KEY_ONE, KEY_TWO, KEY_THREE, KEY_FOUR],
+                [KEY_Q, KEY_W, KEY_E, KEY_R],
+                [KEY_A, KEY_S, KEY_D, KEY_F],
+                [KEY_Z, KEY_X, KEY_C, KEY_V],
+            ],
+        }
+    }
+
+    // what the keys of the block say on this layout
+    fn labels(&self) -> [&'static str; 4] {
+        match self {
+            Layout::Qwerty => ["1 2 3 4", "Q W E R", "A S D F", "Z X C V"],
+            Layout::Qwertz => ["1 2 3 4", "Q W E R", "A S D F", "Y X C V"],
+            Layout::Azerty => ["1 2 3 4", "A Z E R", "Q S D F", "W X C V"],
+            Layout::Dvorak => ["1 2 3 4", "' , . P", "A O E U", "; Q J K"],
+            Layout::Colemak => ["1 2 3 4", "Q W F P", "A R S T", "Z X C D"],
+            Layout::Numpad => ["7 8 9 /", "4 5 6 *", "1 2 3 -", "0 . Enter +"],
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        self.labels().join(" / ")
+    }
+
+    // the character a key of the block types on this layout, for frontends that only get characters
+    pub fn typed_char(&self, key: KeyboardKey) -> Option<char> {
+        self.keys().iter().zip(self.labels()).find_map(|(keys, labels)| {
+            let column = keys.iter().position(|k| *k == key)?;
+            let mut label = labels.split(' ').nth(column)?.chars();
+            match (label.next(), label.next()) {
+                (Some(c), None) => Some(c.to_ascii_lowercase()),
+                _ => None,
+            }
+        })
+    }
+
+    pub fn keybindings(&self) -> Keybindings {
+        let mut keybindings = HashMap::new();
+        for (row, keys) in self.keys().iter().enumerate() {
+            for (column, key) in keys.iter().enumerate() {
+                keybindings.insert(*key, KEYPAD[row][column]);
+            }
+        }
+        keybindings
+    }
+}
+
+// things the emulator itself does, as opposed to keys the chip-8 program sees
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Pause,
+    // one frame at a time while paused
+    FrameAdvance,
+    // hard reset, as if the machine was switched off and on
+    Reset,
+    SoftReset,
+    SaveState,
+    LoadState,
+    // while held
+    FastForward,
+    ToggleFastForward,
+    SlowMotion,
+    RecordGif,
+    Fullscreen,
+    Overlay,
+    Debugger,
+    // one instruction while paused
+    Step,
+    // back to the rom menu, if the game was started from there
+    Menu,
+}
+
+// what the config file looks like, keys are still just names here
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeymapFile {
+    keypad: Option<HashMap<String, Vec<String>>>,
+    gamepad: Option<HashMap<String, Vec<String>>>,
+    actions: HashMap<Action, Vec<String>>,
+    // per-rom profiles, by rom file name without the extension
+    roms: HashMap<String, RomProfile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RomProfile {
+    gamepad: Option<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    pub layout: Layout,
+    pub keypad: Keybindings,
+    pub gamepad: GamepadBindings,
+    pub actions: ActionBindings,
+}
+
+impl Keymap {
+    pub fn new(layout: Layout) -> Keymap {
+        log::info!("Chip-8 keypad on {}", layout.describe());
+        let mut keymap = Keymap::defaults(layout);
+        keymap.drop_colliding_actions();
+        keymap.warn_conflicts();
+        keymap
+    }
+
+    fn defaults(layout: Layout) -> Keymap {
+        Keymap {
+            layout,
+            keypad: layout.keybindings(),
+            gamepad: default_gamepad_bindings(),
+            actions: default_action_bindings(),
+        }
+    }
+
+    // keys of the keypad belong to the game, default actions on them go, e.g. P when a keymap file puts the
+    // keypad on it keeps only the Pause key for pausing
+    fn drop_colliding_actions(&mut self) {
+        let keypad = &self.keypad;
+        self.actions.retain(|key, _| !keypad.contains_key(key));
+    }
+
+    fn warn_conflicts(&self) {
+        for key in self.actions.keys() {
+            if self.keypad.contains_key(key) {
+                log::warn!("{:?} is bound to both a chip-8 key and an action", key);
+            }
+        }
+    }
+
+    // toml, or json if the file ends with .json
+    pub fn load(path: &str, layout: Layout, rom_name: &str) -> Result<Keymap, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read keymap {}: {}", path, err))?;
+        let mut file: KeymapFile = if Path::new(path).extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|err| err.to_string())
+        } else {
+            toml::from_str(&content).map_err(|err| err.to_string())
+        }
+        .map_err(|err| format!("Invalid keymap {}: {}", path, err))?;
+
+        let mut keymap = Keymap::defaults(layout);
+
+        // the keypad section replaces the layout preset as a whole
+        if let Some(keypad) = file.keypad {
+            keymap.keypad.clear();
+            for (chip8_key, names) in keypad {
+                let key = parse_chip8_key(&chip8_key)?;
+                for name in names {
+                    keymap.keypad.insert(parse_key_name(&name)?, key);
+                }
+            }
+        }
+
+        // same for the gamepad, a profile for the running rom wins over the global section
+        let profile = file.roms.remove(rom_name).and_then(|profile| profile.gamepad);
+        if profile.is_some() {
+            log::info!("Using the gamepad profile for {}", rom_name);
+        }
+        if let Some(gamepad) = profile.or(file.gamepad) {
+            keymap.gamepad.clear();
+            for (chip8_key, names) in gamepad {
+                let key = parse_chip8_key(&chip8_key)?;
+                for name in names {
+                    keymap.gamepad.insert(parse_gamepad_input(&name)?, key);
+                }
+            }
+        }
+
+        keymap.drop_colliding_actions();
+
+        // actions are replaced one by one, the rest keep their defaults
+        for (action, names) in file.actions {
+            keymap.actions.retain(|_, bound| *bound != action);
+            for name in names {
+                keymap.actions.insert(parse_key_name(&name)?, action);
+            }
+        }
+
+        keymap.warn_conflicts();
+        Ok(keymap)
+    }
+}
+
+fn parse_chip8_key(name: &str) -> Result<Key, String> {
+    u8::from_str_radix(name, 16)
+        .ok()
+        .and_then(Key::from_u8)
+        .ok_or(format!("Invalid chip-8 key '{}', use 0 - F", name))
+}
+
+fn default_action_bindings() -> ActionBindings {
+    let mut actions = HashMap::new();
+    actions.insert(KeyboardKey::KEY_P, Action::Pause);
+    actions.insert(KeyboardKey::KEY_PAUSE, Action::Pause);
+    actions.insert(KeyboardKey::KEY_N, Action::FrameAdvance);
+    actions.insert(KeyboardKey::KEY_F1, Action::SoftReset);
+    actions.insert(KeyboardKey::KEY_F2, Action::Reset);
+    actions.insert(KeyboardKey::KEY_F5, Action::SaveState);
+    actions.insert(KeyboardKey::KEY_F7, Action::LoadState);
+    actions.insert(KeyboardKey::KEY_TAB, Action::FastForward);
+    actions.insert(KeyboardKey::KEY_F3, Action::SlowMotion);
+    actions.insert(KeyboardKey::KEY_F4, Action::ToggleFastForward);
+    actions.insert(KeyboardKey::KEY_F9, Action::RecordGif);
+    actions.insert(KeyboardKey::KEY_F8, Action::Step);
+    actions.insert(KeyboardKey::KEY_F10, Action::Overlay);
+    actions.insert(KeyboardKey::KEY_F12, Action::Debugger);
+    actions.insert(KeyboardKey::KEY_ESCAPE, Action::Menu);
+    actions.insert(KeyboardKey::KEY_F11, Action::Fullscreen);
+    actions
+}
+
+// raylib key names without the KEY_ prefix, e.g. "Q", "KP_7" or "F5", digits can be written as "1"
+pub fn parse_key_name(name: &str) -> Result<KeyboardKey, String> {
+    let upper = name.trim().to_uppercase();
+    let upper = upper.strip_prefix("KEY_").unwrap_or(&upper);
+    let upper = match upper {
+        "0" => "ZERO",
+        "1" => "ONE",
+        "2" => "TWO",
+        "3" => "THREE",
+        "4" => "FOUR",
+        "5" => "FIVE",
+        "6" => "SIX",
+        "7" => "SEVEN",
+        "8" => "EIGHT",
+        "9" => "NINE",
+        other => other,
+    };
+    key_from_name(upper).ok_or(format!("Unknown key name '{}'", name))
+}
+
+fn key_from_name(name: &str) -> Option<KeyboardKey> {
+    match name {
+        "APOSTROPHE" => Some(KeyboardKey::KEY_APOSTROPHE),
+        "COMMA" => Some(KeyboardKey::KEY_COMMA),
+        "MINUS" => Some(KeyboardKey::KEY_MINUS),
+        "PERIOD" => Some(KeyboardKey::KEY_PERIOD),
+        "SLASH" => Some(KeyboardKey::KEY_SLASH),
+        "ZERO" => Some(KeyboardKey::KEY_ZERO),
+        "ONE" => Some(KeyboardKey::KEY_ONE),
+        "TWO" => Some(KeyboardKey::KEY_TWO),
+        "THREE" => Some(KeyboardKey::KEY_THREE),
+        "FOUR" => Some(KeyboardKey::KEY_FOUR),
+        "FIVE" => Some(KeyboardKey::KEY_FIVE),
+        "SIX" => Some(KeyboardKey::KEY_SIX),
+        "SEVEN" => Some(KeyboardKey::KEY_SEVEN),
+        "EIGHT" => Some(KeyboardKey::KEY_EIGHT),
+        "NINE" => Some(KeyboardKey::KEY_NINE),
+        "SEMICOLON" => Some(KeyboardKey::KEY_SEMICOLON),
+        "EQUAL" => Some(KeyboardKey::KEY_EQUAL),
+        "A" => Some(KeyboardKey::KEY_A),
+        "B" => Some(KeyboardKey::KEY_B),
+        "C" => Some(KeyboardKey::KEY_C),
+        "D" => Some(KeyboardKey::KEY_D),
+        "E" => Some(KeyboardKey::KEY_E),
+        "F" => Some(KeyboardKey::KEY_F),
+        "G" => Some(KeyboardKey::KEY_G),
+        "H" => Some(KeyboardKey::KEY_H),
+        "I" => Some(KeyboardKey::KEY_I),
+        "J" => Some(KeyboardKey::KEY_J),
+        "K" => Some(KeyboardKey::KEY_K),
+        "L" => Some(KeyboardKey::KEY_L),
+        "M" => Some(KeyboardKey::KEY_M),
+        "N" => Some(KeyboardKey::KEY_N),
+        "O" => Some(KeyboardKey::KEY_O),
+        "P" => Some(KeyboardKey::KEY_P),
+        "Q" => Some(KeyboardKey::KEY_Q),
+        "R" => Some(KeyboardKey::KEY_R),
+        "S" => Some(KeyboardKey::KEY_S),
+        "T" => Some(KeyboardKey::KEY_T),
+        "U" => Some(KeyboardKey::KEY_U),
+        "V" => Some(KeyboardKey::KEY_V),
+        "W" => Some(KeyboardKey::KEY_W),
+        "X" => Some(KeyboardKey::KEY_X),
+        "Y" => Some(KeyboardKey::KEY_Y),
+        "Z" => Some(KeyboardKey::KEY_Z),
+        "LEFT_BRACKET" => Some(KeyboardKey::KEY_LEFT_BRACKET),
+        "BACKSLASH" => Some(KeyboardKey::KEY_BACKSLASH),
+        "RIGHT_BRACKET" => Some(KeyboardKey::KEY_RIGHT_BRACKET),
+        "GRAVE" => Some(KeyboardKey::KEY_GRAVE),
+        "SPACE" => Some(KeyboardKey::KEY_SPACE),
+        "ESCAPE" => Some(KeyboardKey::KEY_ESCAPE),
+        "ENTER" => Some(KeyboardKey::KEY_ENTER),
+        "TAB" => Some(KeyboardKey::KEY_TAB),
+        "BACKSPACE" => Some(KeyboardKey::KEY_BACKSPACE),
+        "INSERT" => Some(KeyboardKey::KEY_INSERT),
+        "DELETE" => Some(KeyboardKey::KEY_DELETE),
+        "RIGHT" => Some(KeyboardKey::KEY_RIGHT),
+        "LEFT" => Some(KeyboardKey::KEY_LEFT),
+        "DOWN" => Some(KeyboardKey::KEY_DOWN),
+        "UP" => Some(KeyboardKey::KEY_UP),
+        "PAGE_UP" => Some(KeyboardKey::KEY_PAGE_UP),
+        "PAGE_DOWN" => Some(KeyboardKey::KEY_PAGE_DOWN),
+        "HOME" => Some(KeyboardKey::KEY_HOME),
+        "END" => Some(KeyboardKey::KEY_END),
+        "CAPS_LOCK" => Some(KeyboardKey::KEY_CAPS_LOCK),
+        "SCROLL_LOCK" => Some(KeyboardKey::KEY_SCROLL_LOCK),
+        "NUM_LOCK" => Some(KeyboardKey::KEY_NUM_LOCK),
+        "PRINT_SCREEN" => Some(KeyboardKey::KEY_PRINT_SCREEN),
+        "PAUSE" => Some(KeyboardKey::KEY_PAUSE),
+        "F1" => Some(KeyboardKey::KEY_F1),
+        "F2" => Some(KeyboardKey::KEY_F2),
+        "F3" => Some(KeyboardKey::KEY_F3),
+        "F4" => Some(KeyboardKey::KEY_F4),
+        "F5" => Some(KeyboardKey::KEY_F5),
+        "F6" => Some(KeyboardKey::KEY_F6),
+        "F7" => Some(KeyboardKey::KEY_F7),
+        "F8" => Some(KeyboardKey::KEY_F8),
+        "F9" => Some(KeyboardKey::KEY_F9),
+        "F10" => Some(KeyboardKey::KEY_F10),
+        "F11" => Some(KeyboardKey::KEY_F11),
+        "F12" => Some(KeyboardKey::KEY_F12),
+        "LEFT_SHIFT" => Some(KeyboardKey::KEY_LEFT_SHIFT),
+        "LEFT_CONTROL" => Some(KeyboardKey::KEY_LEFT_CONTROL),
+        "LEFT_ALT" => Some(KeyboardKey::KEY_LEFT_ALT),
+        "LEFT_SUPER" => Some(KeyboardKey::KEY_LEFT_SUPER),
+        "RIGHT_SHIFT" => Some(KeyboardKey::KEY_RIGHT_SHIFT),
+        "RIGHT_CONTROL" => Some(KeyboardKey::KEY_RIGHT_CONTROL),
+        "RIGHT_ALT" => Some(KeyboardKey::KEY_RIGHT_ALT),
+        "RIGHT_SUPER" => Some(KeyboardKey::KEY_RIGHT_SUPER),
+        "KB_MENU" => Some(KeyboardKey::KEY_KB_MENU),
+        "KP_0" => Some(KeyboardKey::KEY_KP_0),
+        "KP_1" => Some(KeyboardKey::KEY_KP_1),
+        "KP_2" => Some(KeyboardKey::KEY_KP_2),
+        "KP_3" => Some(KeyboardKey::KEY_KP_3),
+        "KP_4" => Some(KeyboardKey::KEY_KP_4),
+        "KP_5" => Some(KeyboardKey::KEY_KP_5),
+        "KP_6" => Some(KeyboardKey::KEY_KP_6),
+        "KP_7" => Some(KeyboardKey::KEY_KP_7),
+        "KP_8" => Some(KeyboardKey::KEY_KP_8),
+        "KP_9" => Some(KeyboardKey::KEY_KP_9),
+        "KP_DECIMAL" => Some(KeyboardKey::KEY_KP_DECIMAL),
+        "KP_DIVIDE" => Some(KeyboardKey::KEY_KP_DIVIDE),
+        "KP_MULTIPLY" => Some(KeyboardKey::KEY_KP_MULTIPLY),
+        "KP_SUBTRACT" => Some(KeyboardKey::KEY_KP_SUBTRACT),
+        "KP_ADD" => Some(KeyboardKey::KEY_KP_ADD),
+        "KP_ENTER" => Some(KeyboardKey::KEY_KP_ENTER),
+        "KP_EQUAL" => Some(KeyboardKey::KEY_KP_EQUAL),
+        _ => None,
+    }
+}
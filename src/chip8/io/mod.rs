@@ -1,3 +1,13 @@
+pub mod beeper;
+pub mod debug_panels;
 pub mod display;
+pub mod filter;
+pub mod gamepad;
 pub mod digits;
-pub mod keyboard;
\ No newline at end of file
+pub mod keyboard;
+pub mod keymap;
+pub mod overlay;
+pub mod palette;
+pub mod raylib_input;
+pub mod scaling;
+pub mod terminal_input;
\ No newline at end of file
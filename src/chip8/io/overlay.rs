@@ -0,0 +1,153 @@
+use crate::chip8::io::keyboard::Keyboard;
+use crate::chip8::io::keymap::KEYPAD;
+use crate::chip8::scheduler::FRAME_RATE;
+use raylib::prelude::{measure_text, Color, RaylibDraw};
+use std::time::{Duration, Instant};
+
+// the numbers are averaged over this long, short enough to follow and long enough to read
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+// how long a message like "State saved" stays on screen
+const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+const FONT_SIZE: i32 = 20;
+const LINE_HEIGHT: i32 = 24;
+const PADDING: i32 = 8;
+const KEY_SIZE: i32 = 24;
+const KEY_GAP: i32 = 2;
+const BACKGROUND: Color = Color::new(0, 0, 0, 160);
+const TEXT: Color = Color::WHITE;
+const KEY_UP: Color = Color::new(80, 80, 80, 255);
+
+// what the overlay shows that it doesn't measure itself
+pub struct Status<'a> {
+    pub rom: &'a str,
+    pub platform: &'a str,
+    pub paused: bool,
+    // frame clock multiplier, see FrameClock::tick
+    pub speed: f64,
+    pub keyboard: &'a Keyboard,
+}
+
+// speed, fps and the keypad in the top left corner, messages at the bottom
+pub struct Overlay {
+    pub visible: bool,
+    fps: f64,
+    // emulated frames per real second, 1.0 is full speed
+    emulated_speed: f64,
+    instructions_per_second: f64,
+    sample_start: Instant,
+    sample_frames: u64,
+    // the machine's frame and instruction counters when the sample started
+    sample_emulated_frames: u64,
+    sample_instructions: u64,
+    message: Option<(String, Instant)>,
+}
+
+impl Overlay {
+    pub fn new(visible: bool) -> Overlay {
+        Overlay {
+            visible,
+            fps: 0.0,
+            emulated_speed: 0.0,
+            instructions_per_second: 0.0,
+            sample_start: Instant::now(),
+            sample_frames: 0,
+            sample_emulated_frames: 0,
+            sample_instructions: 0,
+            message: None,
+        }
+    }
+
+    pub fn show_message(&mut self, message: &str) {
+        self.message = Some((message.to_string(), Instant::now()));
+    }
+
+    // call once per frame with the machine's counters, returns whether there is anything to draw,
+    // an expired message still needs one more frame to be erased
+    pub fn update(&mut self, emulated_frames: u64, instructions: u64) -> bool {
+        self.sample_frames += 1;
+        let elapsed = self.sample_start.elapsed();
+        if elapsed >= SAMPLE_INTERVAL {
+            let seconds = elapsed.as_secs_f64();
+            // a reset starts the counters over, that sample is simply lost
+            let emulated_frames_run = emulated_frames.saturating_sub(self.sample_emulated_frames);
+            let instructions_run = instructions.saturating_sub(self.sample_instructions);
+            self.fps = self.sample_frames as f64 / seconds;
+            self.emulated_speed = emulated_frames_run as f64 / FRAME_RATE as f64 / seconds;
+            self.instructions_per_second = instructions_run as f64 / seconds;
+            self.sample_start = Instant::now();
+            self.sample_frames = 0;
+            self.sample_emulated_frames = emulated_frames;
+            self.sample_instructions = instructions;
+        }
+
+        let had_message = self.message.is_some();
+        if self.message.as_ref().is_some_and(|(_, shown)| shown.elapsed() >= MESSAGE_DURATION) {
+            self.message = None;
+        }
+        self.visible || had_message
+    }
+
+    pub fn draw(&self, handle: &mut impl RaylibDraw, status: &Status, window: (i32, i32)) {
+        if self.visible {
+            self.draw_panel(handle, status);
+        }
+        if let Some((message, _)) = &self.message {
+            let width = measure_text(message, FONT_SIZE) + 2 * PADDING;
+            let height = FONT_SIZE + 2 * PADDING;
+            let x = (window.0 - width) / 2;
+            let y = window.1 - height - PADDING;
+            handle.draw_rectangle(x, y, width, height, BACKGROUND);
+            handle.draw_text(message, x + PADDING, y + PADDING, FONT_SIZE, TEXT);
+        }
+    }
+
+    fn draw_panel(&self, handle: &mut impl RaylibDraw, status: &Status) {
+        let state = if status.paused {
+            "Paused".to_string()
+        } else if status.speed > 1.0 {
+            format!("Fast forward {}x", status.speed)
+        } else if status.speed < 1.0 {
+            format!("Slow motion {}x", status.speed)
+        } else {
+            "Running".to_string()
+        };
+        let lines = [
+            format!("{} ({})", status.rom, status.platform),
+            format!(
+                "{:.0} % - {:.0} instructions/s",
+                self.emulated_speed * 100.0,
+                self.instructions_per_second
+            ),
+            format!("{:.0} fps", self.fps),
+            state,
+        ];
+
+        let keypad_size = 4 * KEY_SIZE + 3 * KEY_GAP;
+        let text_width = lines.iter().map(|line| measure_text(line, FONT_SIZE)).max().unwrap_or(0);
+        let width = text_width.max(keypad_size) + 2 * PADDING;
+        let height = lines.len() as i32 * LINE_HEIGHT + keypad_size + 3 * PADDING;
+        handle.draw_rectangle(0, 0, width, height, BACKGROUND);
+
+        let mut y = PADDING;
+        for line in lines.iter() {
+            handle.draw_text(line, PADDING, y, FONT_SIZE, TEXT);
+            y += LINE_HEIGHT;
+        }
+
+        // laid out like the VIP's keypad, held keys are lit
+        y += PADDING;
+        for (row, keys) in KEYPAD.iter().enumerate() {
+            for (column, key) in keys.iter().enumerate() {
+                let x = PADDING + column as i32 * (KEY_SIZE + KEY_GAP);
+                let key_y = y + row as i32 * (KEY_SIZE + KEY_GAP);
+                let held = status.keyboard.is_pressed(*key as u8);
+                let (fill, label_color) = if held { (TEXT, Color::BLACK) } else { (KEY_UP, TEXT) };
+                handle.draw_rectangle(x, key_y, KEY_SIZE, KEY_SIZE, fill);
+                let label = format!("{:X}", *key as u8);
+                let label_x = x + (KEY_SIZE - measure_text(&label, FONT_SIZE)) / 2;
+                let label_y = key_y + (KEY_SIZE - FONT_SIZE) / 2;
+                handle.draw_text(&label, label_x, label_y, FONT_SIZE, label_color);
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::chip8::io::gamepad::Gamepads;
+use crate::chip8::io::keyboard::InputSource;
+use crate::chip8::io::keymap::Keybindings;
+use raylib::RaylibHandle;
+
+// the window's keyboard and any gamepads plugged in, read fresh every frame
+pub struct RaylibInput<'a> {
+    rl: &'a RaylibHandle,
+    keybindings: &'a Keybindings,
+    gamepads: &'a mut Gamepads,
+}
+
+impl<'a> RaylibInput<'a> {
+    pub fn new(
+        rl: &'a RaylibHandle,
+        keybindings: &'a Keybindings,
+        gamepads: &'a mut Gamepads,
+    ) -> RaylibInput<'a> {
+        RaylibInput {
+            rl,
+            keybindings,
+            gamepads,
+        }
+    }
+}
+
+impl InputSource for RaylibInput<'_> {
+    fn poll(&mut self, held: &mut [bool; 16]) {
+        for (key, chip8_key) in self.keybindings.iter() {
+            // pressed catches taps that went down and up again within one frame
+            if self.rl.is_key_down(*key) || self.rl.is_key_pressed(*key) {
+                held[*chip8_key as usize] = true;
+            }
+        }
+
+        for (i, pressed) in self.gamepads.poll(self.rl).into_iter().enumerate() {
+            held[i] |= pressed;
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scaling {
+    // every chip-8 pixel is the same whole number of screen pixels, sharp at any size
+    #[default]
+    Integer,
+    // as large as the window allows, pixels may differ by one screen pixel
+    Fit,
+}
+
+// where the display goes in the window, anything outside of it is letterbox
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    // size of one chip-8 pixel in screen pixels
+    pub pixel_width: f32,
+    pub pixel_height: f32,
+    columns: usize,
+    rows: usize,
+}
+
+impl Viewport {
+    // pixel_aspect is width / height of one chip-8 pixel, 1.0 for square pixels
+    pub fn new(
+        window: (i32, i32),
+        display: (usize, usize),
+        scaling: Scaling,
+        pixel_aspect: f32,
+    ) -> Viewport {
+        let (columns, rows) = display;
+        let window_width = window.0.max(1) as f32;
+        let window_height = window.1.max(1) as f32;
+
+        // the largest pixel height for which the whole display still fits
+        let mut pixel_height =
+            (window_height / rows as f32).min(window_width / (columns as f32 * pixel_aspect));
+        if scaling == Scaling::Integer {
+            // with non-square pixels the aspect is as close as whole pixels allow
+            pixel_height = pixel_height.floor().max(1.0);
+        }
+        let mut pixel_width = pixel_height * pixel_aspect;
+        if scaling == Scaling::Integer {
+            pixel_width = pixel_width.floor().max(1.0);
+        }
+
+        let width = pixel_width * columns as f32;
+        let height = pixel_height * rows as f32;
+        Viewport {
+            x: ((window_width - width) / 2.0).floor(),
+            y: ((window_height - height) / 2.0).floor(),
+            pixel_width,
+            pixel_height,
+            columns,
+            rows,
+        }
+    }
+
+    // screen x of every line between two columns and y of every line between two rows
+    pub fn grid_lines(&self) -> (Vec<i32>, Vec<i32>) {
+        let columns = (1..self.columns)
+            .map(|column| (self.x + column as f32 * self.pixel_width).round() as i32)
+            .collect();
+        let rows = (1..self.rows)
+            .map(|row| (self.y + row as f32 * self.pixel_height).round() as i32)
+            .collect();
+        (columns, rows)
+    }
+
+    pub fn width(&self) -> i32 {
+        (self.pixel_width * self.columns as f32).round() as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        (self.pixel_height * self.rows as f32).round() as i32
+    }
+}
@@ -0,0 +1,103 @@
+use crate::chip8::io::keyboard::{InputSource, Key};
+use crate::chip8::io::keymap::Keymap;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// most terminals only report presses, a key counts as held this long after its last press.
+// longer than the usual key repeat delay, so holding a key down keeps it held
+const KEY_HOLD: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyState {
+    Up,
+    // until the terminal reports the release
+    Down,
+    // until the time runs out, for terminals without release events
+    Until(Instant),
+}
+
+// keys typed into the terminal, as far as the terminal tells us about them
+pub struct TerminalInput {
+    keybindings: HashMap<char, Key>,
+    // true if the terminal reports key releases, see crossterm's keyboard enhancement
+    releases: bool,
+    keys: [KeyState; 16],
+    quit: bool,
+}
+
+impl TerminalInput {
+    // a terminal gets characters, not key positions, so the layout tells what the keys of the keypad type. keys a
+    // keymap file adds are taken as what they type on a US keyboard, keys that type nothing printable can't be used
+    pub fn new(keymap: &Keymap, releases: bool) -> TerminalInput {
+        let keybindings = keymap
+            .keypad
+            .iter()
+            .filter_map(|(key, chip8_key)| {
+                // raylib key codes are ascii for printable keys
+                let code = *key as u32;
+                let c = keymap
+                    .layout
+                    .typed_char(*key)
+                    .or(char::from_u32(code).filter(|_| (0x20..0x7F).contains(&code)))?;
+                Some((c.to_ascii_lowercase(), *chip8_key))
+            })
+            .collect();
+
+        TerminalInput {
+            keybindings,
+            releases,
+            keys: [KeyState::Up; 16],
+            quit: false,
+        }
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    // reads everything the terminal has queued up, without blocking
+    pub fn read_events(&mut self) {
+        while crossterm::event::poll(Duration::ZERO).unwrap_or(false) {
+            let Ok(Event::Key(event)) = crossterm::event::read() else {
+                continue;
+            };
+            self.handle_key(event);
+        }
+    }
+
+    fn handle_key(&mut self, event: KeyEvent) {
+        let quit = event.code == KeyCode::Esc
+            || (event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL));
+        if quit {
+            self.quit = true;
+            return;
+        }
+
+        let KeyCode::Char(c) = event.code else {
+            return;
+        };
+        let Some(key) = self.keybindings.get(&c.to_ascii_lowercase()) else {
+            return;
+        };
+
+        self.keys[*key as usize] = match event.kind {
+            KeyEventKind::Release => KeyState::Up,
+            _ if self.releases => KeyState::Down,
+            _ => KeyState::Until(Instant::now() + KEY_HOLD),
+        };
+    }
+}
+
+impl InputSource for TerminalInput {
+    fn poll(&mut self, held: &mut [bool; 16]) {
+        let now = Instant::now();
+        for (i, state) in self.keys.iter().enumerate() {
+            held[i] |= match *state {
+                KeyState::Up => false,
+                KeyState::Down => true,
+                KeyState::Until(until) => until > now,
+            };
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::chip8::chip8::Window;
+use crate::chip8::library::{RomEntry, RomLibrary};
+use crate::chip8::scheduler::FrameClock;
+use raylib::consts::KeyboardKey;
+use raylib::prelude::{Color, RaylibDraw};
+
+const FONT_SIZE: i32 = 20;
+const LINE_HEIGHT: i32 = 24;
+const SMALL_FONT_SIZE: i32 = 10;
+const SMALL_LINE_HEIGHT: i32 = 12;
+const PADDING: i32 = 8;
+// title and search line at the top, details of the selected rom at the bottom
+const HEADER_HEIGHT: i32 = 2 * LINE_HEIGHT + SMALL_LINE_HEIGHT + 2 * PADDING;
+const DETAILS_HEIGHT: i32 = 3 * SMALL_LINE_HEIGHT + 2 * PADDING;
+// descriptions are cut to one line
+const MAX_DESCRIPTION_LENGTH: usize = 100;
+
+const BACKGROUND: Color = Color::new(24, 24, 24, 255);
+const TEXT: Color = Color::LIGHTGRAY;
+const DIM: Color = Color::GRAY;
+const SELECTED: Color = Color::new(60, 60, 90, 255);
+const ERROR: Color = Color::RED;
+
+// the rom menu, shown in the window when no rom was given
+pub struct Launcher<'a> {
+    library: &'a RomLibrary,
+    // typed to narrow the list down, see RomLibrary::search
+    query: String,
+    // the library searched for the query, only redone when the query changes
+    matches: Vec<&'a RomEntry>,
+    selected: usize,
+    // first line on screen
+    scroll: usize,
+    // why the last rom didn't start, shown instead of the details until something is pressed
+    error: Option<String>,
+}
+
+impl<'a> Launcher<'a> {
+    pub fn new(library: &'a RomLibrary) -> Launcher<'a> {
+        Launcher {
+            library,
+            query: String::new(),
+            matches: library.search(""),
+            selected: 0,
+            scroll: 0,
+            error: None,
+        }
+    }
+
+    // the settings of a picked rom can still be invalid, e.g. a clock in its config section
+    pub fn show_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    // until a rom is picked, its path, or the window is closed. The query and selection are kept
+    // for the next time the menu is shown
+    pub fn run(&mut self, window: &mut Window) -> Option<String> {
+        window.0.set_window_title(&window.1, "Rusty Chip-8");
+        window.0.set_exit_key(Some(KeyboardKey::KEY_ESCAPE));
+
+        let mut clock = FrameClock::new();
+        while !window.0.window_should_close() {
+            let visible_lines = Launcher::visible_lines(window.0.get_screen_height());
+            if let Some(path) = self.handle_input(&mut window.0, visible_lines) {
+                return Some(path);
+            }
+            self.draw(window, visible_lines);
+            clock.tick(1.0);
+            clock.wait();
+        }
+        None
+    }
+
+    fn visible_lines(window_height: i32) -> usize {
+        ((window_height - HEADER_HEIGHT - DETAILS_HEIGHT) / LINE_HEIGHT).max(1) as usize
+    }
+
+    fn handle_input(&mut self, rl: &mut raylib::RaylibHandle, visible_lines: usize) -> Option<String> {
+        if rl.get_key_pressed().is_some() {
+            self.error = None;
+        }
+        let mut typed = false;
+        while let Some(character) = rl.get_char_pressed() {
+            self.query.push(character);
+            typed = true;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) && self.query.pop().is_some() {
+            typed = true;
+        }
+        if typed {
+            self.matches = self.library.search(&self.query);
+            self.selected = 0;
+        }
+
+        let last = self.matches.len().saturating_sub(1);
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.selected += 1;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_PAGE_DOWN) {
+            self.selected += visible_lines;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_PAGE_UP) {
+            self.selected = self.selected.saturating_sub(visible_lines);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_HOME) {
+            self.selected = 0;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_END) {
+            self.selected = last;
+        }
+        self.selected = self.selected.min(last);
+
+        // the selected line stays on screen
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible_lines {
+            self.scroll = self.selected + 1 - visible_lines;
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return self.matches.get(self.selected).map(|entry| entry.path.clone());
+        }
+        None
+    }
+
+    fn draw(&self, window: &mut Window, visible_lines: usize) {
+        let width = window.0.get_screen_width();
+        let height = window.0.get_screen_height();
+        let mut handle = window.0.begin_drawing(&window.1);
+        handle.clear_background(BACKGROUND);
+
+        let mut y = PADDING;
+        let header = format!("Rusty Chip-8 - {} of {} ROMs", self.matches.len(), self.library.entries().len());
+        handle.draw_text(&header, PADDING, y, FONT_SIZE, TEXT);
+        y += LINE_HEIGHT;
+        handle.draw_text(&format!("Search: {}_", self.query), PADDING, y, FONT_SIZE, TEXT);
+        y += LINE_HEIGHT;
+        let help = "Type to search, arrows to choose, Enter to start, Esc in a game comes back here, Esc here quits";
+        handle.draw_text(help, PADDING, y, SMALL_FONT_SIZE, DIM);
+        y += SMALL_LINE_HEIGHT + PADDING;
+
+        if self.library.entries().is_empty() {
+            let message = "No ROMs found, put them in rom/ or add directories with --rom-dir";
+            handle.draw_text(message, PADDING, y, FONT_SIZE, TEXT);
+            return;
+        }
+
+        for (index, entry) in self.matches.iter().enumerate().skip(self.scroll).take(visible_lines) {
+            if index == self.selected {
+                handle.draw_rectangle(0, y - 2, width, LINE_HEIGHT, SELECTED);
+            }
+            handle.draw_text(&entry.title, PADDING, y, FONT_SIZE, TEXT);
+            let platform = entry.platform.map(|platform| platform.name()).unwrap_or("?");
+            handle.draw_text(platform, width - 260, y, FONT_SIZE, DIM);
+            handle.draw_text(&format!("{} B", entry.size), width - 100, y, FONT_SIZE, DIM);
+            y += LINE_HEIGHT;
+        }
+
+        if let Some(error) = &self.error {
+            let y = height - DETAILS_HEIGHT + PADDING;
+            handle.draw_text(&format!("Can't start the ROM: {}", error), PADDING, y, SMALL_FONT_SIZE, ERROR);
+        } else if let Some(entry) = self.matches.get(self.selected) {
+            let mut y = height - DETAILS_HEIGHT + PADDING;
+            for line in Launcher::details(entry) {
+                handle.draw_text(&line, PADDING, y, SMALL_FONT_SIZE, DIM);
+                y += SMALL_LINE_HEIGHT;
+            }
+        }
+    }
+
+    // the path, and what the database knows about the rom
+    fn details(entry: &RomEntry) -> Vec<String> {
+        let mut lines = vec![entry.path.clone()];
+        if let Some(info) = &entry.info {
+            if !info.authors.is_empty() {
+                lines.push(format!("By {}, for {}", info.authors.join(", "), info.platforms.join(", ")));
+            }
+            if let Some(description) = &info.description {
+                let mut description = description.lines().next().unwrap_or("").to_string();
+                if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+                    description = description.chars().take(MAX_DESCRIPTION_LENGTH).collect::<String>() + "...";
+                }
+                lines.push(description);
+            }
+        }
+        lines
+    }
+}
@@ -0,0 +1,156 @@
+use crate::chip8::chip8::{rom_hash, rom_name};
+use crate::chip8::config::Config;
+use crate::chip8::database::{RomDatabase, RomInfo};
+use crate::chip8::platform::Platform;
+use std::path::{Path, PathBuf};
+
+// what chip-8 roms are usually called, the extension can be left out when naming a rom
+const ROM_EXTENSION: &str = "ch8";
+
+#[derive(Debug, Clone)]
+pub struct RomEntry {
+    pub path: String,
+    // from the database, or else the file name
+    pub title: String,
+    pub size: u64,
+    // what the rom will run as: its config section's platform, or else the database's
+    pub platform: Option<Platform>,
+    pub info: Option<RomInfo>,
+}
+
+// every rom in the rom directories and the ones below them, sorted by title
+pub struct RomLibrary {
+    entries: Vec<RomEntry>,
+}
+
+impl RomLibrary {
+    pub fn scan(dirs: &[PathBuf], config: &Config, database: &RomDatabase) -> RomLibrary {
+        let mut entries = Vec::new();
+        for path in rom_files(dirs) {
+            let path = path.to_string_lossy().to_string();
+            let hash = rom_hash(&path).ok();
+            let info = hash.as_deref().and_then(|hash| database.get(hash)).cloned();
+            let platform = config
+                .rom_section(&path, hash.as_deref())
+                .and_then(|layer| layer.platform)
+                .or_else(|| info.as_ref().and_then(|info| info.platform()));
+            entries.push(RomEntry {
+                title: info
+                    .as_ref()
+                    .map(|info| info.title.clone())
+                    .filter(|title| !title.is_empty())
+                    .unwrap_or_else(|| rom_name(&path).to_string()),
+                size: std::fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0),
+                path,
+                platform,
+                info,
+            });
+        }
+        entries.sort_by_key(|entry| entry.title.to_lowercase());
+        log::info!("Found {} ROMs", entries.len());
+        RomLibrary { entries }
+    }
+
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    // case-insensitive, matches the title or the path
+    pub fn search(&self, query: &str) -> Vec<&RomEntry> {
+        let query = query.to_lowercase();
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.title.to_lowercase().contains(&query) || entry.path.to_lowercase().contains(&query)
+            })
+            .collect()
+    }
+}
+
+// what --rom accepts: a path, or a name in one of the directories, both with or without the
+// extension, or the name of any rom further down the directories
+pub fn find_rom(rom: &str, dirs: &[PathBuf]) -> Option<String> {
+    let with_extension = format!("{}.{}", rom, ROM_EXTENSION);
+    let mut candidates = vec![PathBuf::from(rom), PathBuf::from(&with_extension)];
+    for dir in dirs {
+        candidates.push(dir.join(rom));
+        candidates.push(dir.join(&with_extension));
+    }
+    if let Some(found) = candidates.into_iter().find(|path| path.is_file()) {
+        return Some(found.to_string_lossy().to_string());
+    }
+
+    rom_files(dirs)
+        .into_iter()
+        .find(|path| rom_name(&path.to_string_lossy()).eq_ignore_ascii_case(rom))
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+fn rom_files(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        collect_rom_files(dir, &mut files);
+    }
+    files
+}
+
+// depth first, in name order so the results are the same every time
+fn collect_rom_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    // the entry's own file type doesn't follow symlinks and is free on most platforms
+    let mut entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Some((entry.path(), entry.file_type().ok()?)))
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            log::debug!("Skipping ROM directory {}: {}", dir.display(), err);
+            return;
+        }
+    };
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (path, file_type) in entries {
+        if file_type.is_dir() {
+            collect_rom_files(&path, files);
+        } else if file_type.is_symlink() && path.is_dir() {
+            // symlinked directories are skipped, one pointing up the tree would never end
+            log::debug!("Skipping symlinked ROM directory {}", path.display());
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(ROM_EXTENSION))
+        {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rom_files_in_name_order() {
+        let dir = std::env::temp_dir().join(format!("rustychip-test-library-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("games")).unwrap();
+        for file in ["b.ch8", "a.CH8", "notes.txt", "games/c.ch8"] {
+            std::fs::write(dir.join(file), [0x00, 0xE0]).unwrap();
+        }
+        // a link back up the tree is skipped instead of followed forever, a linked rom is kept
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&dir, dir.join("games/up")).unwrap();
+            std::os::unix::fs::symlink(dir.join("b.ch8"), dir.join("games/d.ch8")).unwrap();
+        }
+
+        let files = rom_files(std::slice::from_ref(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| file.strip_prefix(&dir).unwrap().to_string_lossy().into_owned())
+            .collect();
+        let mut expected = vec!["a.CH8", "b.ch8", "games/c.ch8"];
+        if cfg!(unix) {
+            expected.push("games/d.ch8");
+        }
+        assert_eq!(names, expected);
+    }
+}
@@ -0,0 +1,357 @@
+use crate::chip8::cpu::cpu::{CpuSnapshot, CpuState, Profile};
+use crate::chip8::cpu::rng::Rng;
+use crate::chip8::cpu::timing::{Timing, CLOCKS_PER_MACHINE_CYCLE, INTERRUPT_CYCLES};
+use crate::chip8::cpu::Cpu;
+use crate::chip8::datatypes::datatypes::Address;
+use crate::chip8::io::display::Display;
+use crate::chip8::io::keyboard::{KeyEvent, Keyboard};
+use crate::chip8::ram::Ram;
+use crate::chip8::scheduler::FRAME_RATE;
+use crate::chip8::ChipSettings;
+use std::cell::{Ref, RefCell};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+#[derive(Debug, Clone)]
+pub struct SaveState {
+    cpu: CpuState,
+    ram: Ram,
+    display: Display,
+}
+
+// everything that makes up the emulated computer, without any window attached
+pub struct Machine {
+    cpu: Cpu,
+    ram: Rc<RefCell<Ram>>,
+    display: Rc<RefCell<Display>>,
+    keyboard: Rc<RefCell<Keyboard>>,
+    cpu_rate: u64,
+    timers_rate: u64,
+    timing: Timing,
+    // leftover cpu cycles and timer ticks, in 1/60 s units
+    cycles_budget: u64,
+    timers_budget: u64,
+    // vip timing only: 1802 machine cycles left in this frame, below zero when the last
+    // instruction ran into the next one
+    vip_cycles: i64,
+    // 60 Hz frames run so far, see run_frame
+    frame: u64,
+    instructions: u64,
+    // the benchmark's --instructions, checked before every instruction
+    instruction_limit: Option<u64>,
+    profile: Option<Profile>,
+    // addresses the emulation stops at before running the instruction there, see run_frame
+    breakpoints: BTreeSet<u16>,
+    // where the last frame stopped, until the frontend takes it
+    breakpoint_hit: Option<u16>,
+    // the instruction a breakpoint stopped at runs when the emulation continues
+    resume_at: Option<u16>,
+}
+
+impl Machine {
+    pub fn new(settings: &ChipSettings) -> Machine {
+        let display = Rc::new(RefCell::new(Display::new()));
+        let keyboard = Rc::new(RefCell::new(Keyboard::new()));
+        let ram = Rc::new(RefCell::new(Ram::new()));
+        ram.borrow_mut().load_rom(settings.rom.as_str());
+
+        let rng = Rng::new(settings.rng, settings.seed);
+        let cpu = Cpu::new(
+            display.clone(),
+            ram.clone(),
+            keyboard.clone(),
+            rng,
+            settings.display_wait,
+        );
+
+        Machine {
+            cpu,
+            ram,
+            display,
+            keyboard,
+            cpu_rate: settings.cpu_rate,
+            timers_rate: settings.timers_rate,
+            timing: settings.timing,
+            cycles_budget: 0,
+            timers_budget: 0,
+            vip_cycles: 0,
+            frame: 0,
+            instructions: 0,
+            instruction_limit: None,
+            profile: None,
+            breakpoints: BTreeSet::new(),
+            breakpoint_hit: None,
+            resume_at: None,
+        }
+    }
+
+    pub fn cycle(&mut self) -> u64 {
+        if self.cpu.is_running() {
+            self.instructions += 1;
+        }
+        match &mut self.profile {
+            Some(profile) => self.cpu.cycle_profiled(profile),
+            None => self.cpu.cycle(),
+        }
+    }
+
+    // instructions run so far, waiting for a key or the display doesn't count
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    // frames stop early once this many instructions ran, the machine doesn't get any further
+    pub fn set_instruction_limit(&mut self, limit: u64) {
+        self.instruction_limit = Some(limit);
+    }
+
+    fn at_instruction_limit(&self) -> bool {
+        self.instruction_limit.is_some_and(|limit| self.instructions >= limit)
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.cpu.is_waiting_for_key()
+    }
+
+    // measure every instruction from now on, it slows the emulation down noticeably
+    pub fn start_profiling(&mut self) {
+        self.profile = Some(Profile::default());
+    }
+
+    pub fn profile(&self) -> Option<Profile> {
+        self.profile
+    }
+
+    pub fn update_timers(&mut self) {
+        self.cpu.update_timers();
+    }
+
+    // one 60 Hz frame of cycles and timer ticks, always the same for the same settings,
+    // leftovers carry over so any clock from 1 Hz up averages out exactly, returns whether the buzzer was on at each timer tick
+    pub fn run_frame(&mut self) -> Vec<bool> {
+        let frame_rate = FRAME_RATE as u64;
+        self.cpu.vblank();
+        match self.timing {
+            Timing::Instructions => {
+                self.cycles_budget += self.cpu_rate;
+                while self.cycles_budget >= frame_rate {
+                    if self.at_instruction_limit() {
+                        break;
+                    }
+                    if self.at_breakpoint() {
+                        // the rest of the frame is dropped, resuming mustn't run it on top of the next one
+                        self.cycles_budget %= frame_rate;
+                        break;
+                    }
+                    self.cycles_budget -= frame_rate;
+                    self.cycle();
+                    if self.cpu.is_waiting_for_vblank() {
+                        // the rest of the frame's cycles are spent waiting, no need to run them
+                        self.cycles_budget %= frame_rate;
+                        break;
+                    }
+                }
+            }
+            Timing::Vip => self.run_vip_cycles(),
+        }
+
+        let mut beeps = Vec::new();
+        self.timers_budget += self.timers_rate;
+        while self.timers_budget >= frame_rate {
+            self.timers_budget -= frame_rate;
+            beeps.push(self.should_beep());
+            self.update_timers();
+        }
+
+        self.frame += 1;
+        beeps
+    }
+
+    // cpu_rate is the 1802 clock here, the frame's machine cycles minus the display interrupt
+    // are spent on instructions at the price they had on the VIP
+    fn run_vip_cycles(&mut self) {
+        let clocks_per_frame = CLOCKS_PER_MACHINE_CYCLE * FRAME_RATE as u64;
+        self.cycles_budget += self.cpu_rate;
+        let frame_cycles = self.cycles_budget / clocks_per_frame;
+        self.cycles_budget %= clocks_per_frame;
+
+        self.vip_cycles += frame_cycles as i64 - INTERRUPT_CYCLES as i64;
+        while self.vip_cycles > 0 {
+            if self.at_instruction_limit() {
+                break;
+            }
+            if self.at_breakpoint() {
+                // same as with instructions timing, the rest of the frame is dropped
+                self.vip_cycles = 0;
+                break;
+            }
+            let cycles = self.cycle() as i64;
+            if self.cpu.is_waiting_for_vblank() {
+                // the rest of the frame is spent waiting, the drawing itself happens after the interrupt
+                self.vip_cycles = -cycles;
+                break;
+            }
+            self.vip_cycles -= cycles;
+        }
+    }
+
+    fn at_breakpoint(&mut self) -> bool {
+        if self.breakpoints.is_empty() {
+            return false;
+        }
+        let pc = self.cpu.pc().as_u16();
+        if self.resume_at.take() == Some(pc) || !self.breakpoints.contains(&pc) {
+            return false;
+        }
+        self.resume_at = Some(pc);
+        self.breakpoint_hit = Some(pc);
+        true
+    }
+
+    // one instruction on its own, outside of any frame, breakpoints don't apply
+    pub fn step(&mut self) {
+        self.resume_at = None;
+        // a step has no end of frame to wait for, a DRW waiting for it is done
+        if self.cpu.is_waiting_for_vblank() {
+            self.cpu.vblank();
+        }
+        self.cycle();
+    }
+
+    pub fn toggle_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    // the address of the breakpoint the last frame stopped at, if any
+    pub fn take_breakpoint_hit(&mut self) -> Option<u16> {
+        self.breakpoint_hit.take()
+    }
+
+    pub fn cpu(&self) -> CpuSnapshot {
+        self.cpu.snapshot()
+    }
+
+    pub fn ram(&self) -> Ref<'_, Ram> {
+        self.ram.borrow()
+    }
+
+    // addresses written to lately, the newest last
+    pub fn recent_writes(&self) -> Vec<u16> {
+        self.cpu.recent_writes().iter().map(|address| address.as_u16()).collect()
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // fnv-1a over memory, screen and cpu, cheap enough to compare runs every second
+    pub fn checksum(&self) -> u64 {
+        let ram = self.ram.borrow();
+        let display = self.display.borrow();
+        let bytes = (0..4096)
+            .map(|address| ram.read(Address(address)).0)
+            .chain(display.get_display())
+            .chain(self.cpu.save_state().bytes());
+
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+
+    // the keypad is left alone, it reflects what is held down right now, the rng is part of the cpu
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            cpu: self.cpu.save_state(),
+            ram: *self.ram.borrow(),
+            display: *self.display.borrow(),
+        }
+    }
+
+    pub fn load_state(&mut self, state: &SaveState) {
+        self.cpu.load_state(&state.cpu);
+        *self.ram.borrow_mut() = state.ram;
+        *self.display.borrow_mut() = state.display;
+        self.display.borrow_mut().mark_dirty();
+    }
+
+    // like the reset switch: the rom is loaded again and the cpu starts over, the rest of memory
+    // stays as it was, unlike Machine::new which is a power cycle
+    pub fn soft_reset(&mut self, settings: &ChipSettings) {
+        self.ram.borrow_mut().load_rom(settings.rom.as_str());
+        self.cpu.reset();
+        self.display.borrow_mut().clear();
+        // leftovers of the frame before the reset don't carry over, like after a hard reset
+        self.cycles_budget = 0;
+        self.timers_budget = 0;
+        self.vip_cycles = 0;
+    }
+
+    pub fn should_beep(&self) -> bool {
+        self.cpu.should_beep()
+    }
+
+    pub fn display(&self) -> Ref<'_, Display> {
+        self.display.borrow()
+    }
+
+    // rows of the display changed since the last call, see Display::take_dirty_rows
+    pub fn take_dirty_rows(&self) -> u64 {
+        self.display.borrow_mut().take_dirty_rows()
+    }
+
+    pub fn keyboard(&self) -> &Rc<RefCell<Keyboard>> {
+        &self.keyboard
+    }
+
+    pub fn take_key_events(&self) -> Vec<KeyEvent> {
+        let events = self.keyboard.borrow_mut().take_events();
+        for event in events.iter() {
+            let edge = if event.pressed { "down" } else { "up" };
+            log::debug!("Key {:?} {}", event.key, edge);
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::config::SettingsLayer;
+
+    fn machine_with(name: &str, program: &[u8]) -> Machine {
+        let path = std::env::temp_dir().join(format!("rustychip-test-{}-{}.ch8", name, std::process::id()));
+        std::fs::write(&path, program).unwrap();
+        let layer = SettingsLayer {
+            display_wait: Some(true),
+            seed: Some(0),
+            ..Default::default()
+        };
+        let settings = ChipSettings::builder(path.to_string_lossy().into_owned())
+            .layer(&layer)
+            .build()
+            .unwrap();
+        let machine = Machine::new(&settings);
+        std::fs::remove_file(&path).unwrap();
+        machine
+    }
+
+    #[test]
+    fn step_runs_past_a_drw_waiting_for_vblank() {
+        // DRW V0, V0, 1 then LD V1, 0x42
+        let mut machine = machine_with("step", &[0xD0, 0x01, 0x61, 0x42]);
+        machine.step();
+        assert_eq!(machine.cpu().pc, 0x202);
+        machine.step();
+        assert_eq!(machine.cpu().pc, 0x204);
+        assert_eq!(machine.cpu().v[1], 0x42);
+    }
+}
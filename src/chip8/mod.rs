@@ -1,8 +1,22 @@
+pub mod bench;
 pub mod chip8;
+pub mod config;
 pub mod cpu;
+pub mod database;
 pub mod datatypes;
+pub mod debugger;
+pub mod headless;
 pub mod io;
+pub mod launcher;
+pub mod library;
+pub mod machine;
+pub mod platform;
 pub mod ram;
+pub mod record;
+pub mod scheduler;
+pub mod terminal;
 pub mod util;
 pub use chip8::Chip8;
-pub use chip8::ChipSettings;
\ No newline at end of file
+pub use chip8::ChipSettings;
+pub use headless::Headless;
+pub use terminal::Terminal;
\ No newline at end of file
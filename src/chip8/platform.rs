@@ -0,0 +1,42 @@
+use crate::chip8::cpu::rng::RngKind;
+use crate::chip8::cpu::timing::Timing;
+use serde::Deserialize;
+
+// which machine the rom was written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    // what most modern chip-8 roms expect
+    #[default]
+    Chip8,
+    // the original COSMAC VIP interpreter
+    Vip,
+}
+
+impl Platform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::Chip8 => "CHIP-8",
+            Platform::Vip => "COSMAC VIP",
+        }
+    }
+
+    pub fn default_rng(&self) -> RngKind {
+        match self {
+            Platform::Chip8 => RngKind::Xorshift,
+            Platform::Vip => RngKind::Vip,
+        }
+    }
+
+    pub fn default_timing(&self) -> Timing {
+        match self {
+            Platform::Chip8 => Timing::Instructions,
+            Platform::Vip => Timing::Vip,
+        }
+    }
+
+    // the VIP interpreter waited for the display interrupt before drawing a sprite
+    pub fn default_display_wait(&self) -> bool {
+        *self == Platform::Vip
+    }
+}
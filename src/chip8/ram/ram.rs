@@ -3,7 +3,7 @@ use crate::chip8::io::digits::Digits;
 use std::fs::File;
 use std::io::Read;
 
-const MEMORY_SIZE: usize = 4096;
+pub const MEMORY_SIZE: usize = 4096;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Ram {
@@ -0,0 +1,97 @@
+use crate::chip8::io::display::{Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::chip8::io::palette::Colors;
+use gif::{Encoder, EncodingError, Frame, Repeat};
+use std::fs::File;
+use std::io::BufWriter;
+
+// every chip-8 pixel becomes a GIF_SCALE x GIF_SCALE block, 64x32 is too small to look at
+const GIF_SCALE: usize = 4;
+const FRAMES_PER_SECOND: u64 = 60;
+
+pub struct GifRecorder {
+    encoder: Encoder<BufWriter<File>>,
+    path: String,
+    // frame waiting to be written, with the number of 60 Hz frames it has been shown for
+    pending: Option<(Vec<u8>, u64)>,
+    // 60 Hz frames already written to the file
+    elapsed: u64,
+}
+
+impl GifRecorder {
+    pub fn new(path: &str, colors: Colors) -> Result<GifRecorder, EncodingError> {
+        let file = BufWriter::new(File::create(path)?);
+        // pixel value is the palette index, 0 = background, 1 = foreground
+        let palette = colors
+            .iter()
+            .flat_map(|color| [(color >> 16) as u8, (color >> 8) as u8, *color as u8])
+            .collect::<Vec<u8>>();
+        let mut encoder = Encoder::new(
+            file,
+            (DISPLAY_WIDTH * GIF_SCALE) as u16,
+            (DISPLAY_HEIGHT * GIF_SCALE) as u16,
+            &palette,
+        )?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        Ok(GifRecorder {
+            encoder,
+            path: path.to_string(),
+            pending: None,
+            elapsed: 0,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // call once per 60 Hz frame, identical consecutive frames are merged into one
+    pub fn capture(&mut self, display: &Display) -> Result<(), EncodingError> {
+        let pixels = display.get_display();
+        if let Some((frame, shown)) = &mut self.pending {
+            if frame.as_slice() == pixels {
+                *shown += 1;
+                return Ok(());
+            }
+        }
+        self.flush()?;
+        self.pending = Some((pixels.to_vec(), 1));
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), EncodingError> {
+        self.flush()?;
+        self.encoder.into_inner()?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), EncodingError> {
+        let Some((pixels, shown)) = self.pending.take() else {
+            return Ok(());
+        };
+
+        // delays are in 1/100 s, deriving them from absolute timestamps keeps the total length exact
+        let start = self.elapsed * 100 / FRAMES_PER_SECOND;
+        self.elapsed += shown;
+        let end = self.elapsed * 100 / FRAMES_PER_SECOND;
+
+        let width = DISPLAY_WIDTH * GIF_SCALE;
+        let mut buffer = vec![0; width * DISPLAY_HEIGHT * GIF_SCALE];
+        for (i, &pixel) in pixels.iter().enumerate() {
+            let x = (i % DISPLAY_WIDTH) * GIF_SCALE;
+            let y = (i / DISPLAY_WIDTH) * GIF_SCALE;
+            for row in y..y + GIF_SCALE {
+                buffer[row * width + x..row * width + x + GIF_SCALE].fill(pixel);
+            }
+        }
+
+        let frame = Frame {
+            width: width as u16,
+            height: (DISPLAY_HEIGHT * GIF_SCALE) as u16,
+            delay: (end - start).min(u16::MAX as u64) as u16,
+            buffer: buffer.into(),
+            ..Frame::default()
+        };
+        self.encoder.write_frame(&frame)
+    }
+}
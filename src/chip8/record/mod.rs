@@ -0,0 +1,6 @@
+pub mod gif_recorder;
+pub mod movie;
+pub mod wav_recorder;
+pub use gif_recorder::GifRecorder;
+pub use movie::Movie;
+pub use wav_recorder::WavRecorder;
\ No newline at end of file
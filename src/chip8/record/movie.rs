@@ -0,0 +1,227 @@
+use crate::chip8::chip8::{rom_hash, rom_name};
+use crate::chip8::cpu::rng::RngKind;
+use crate::chip8::cpu::timing::Timing;
+use crate::chip8::io::keyboard::Key;
+use crate::chip8::machine::Machine;
+use crate::chip8::ChipSettings;
+use serde::{Deserialize, Serialize};
+
+const MOVIE_VERSION: u32 = 1;
+// how often the machine state is written down to catch desyncs, in frames
+const CHECKPOINT_INTERVAL: u64 = 60;
+
+// one keypad change, applied right before the frame runs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct MovieEvent {
+    frame: u64,
+    key: u8,
+    pressed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Checkpoint {
+    frame: u64,
+    checksum: u64,
+}
+
+// what ends up in the file, json
+#[derive(Debug, Serialize, Deserialize)]
+struct MovieFile {
+    version: u32,
+    rom: String,
+    rom_hash: String,
+    cpu_rate: u64,
+    timers_rate: u64,
+    #[serde(default)]
+    timing: Timing,
+    #[serde(default)]
+    display_wait: bool,
+    #[serde(default)]
+    rng: RngKind,
+    seed: Option<u64>,
+    frames: u64,
+    events: Vec<MovieEvent>,
+    checkpoints: Vec<Checkpoint>,
+}
+
+// records the keypad frame by frame, or plays a recording back into the keypad
+pub struct Movie {
+    file: MovieFile,
+    path: String,
+    playing: bool,
+    next_event: usize,
+    next_checkpoint: usize,
+    desynced: bool,
+}
+
+impl Movie {
+    pub fn record(path: &str, settings: &ChipSettings) -> Result<Movie, String> {
+        let hash = rom_hash(&settings.rom)
+            .map_err(|err| format!("Failed to read ROM {}: {}", settings.rom, err))?;
+        // fail now rather than after a long session
+        std::fs::File::create(path)
+            .map_err(|err| format!("Failed to create movie {}: {}", path, err))?;
+
+        Ok(Movie {
+            file: MovieFile {
+                version: MOVIE_VERSION,
+                rom: rom_name(&settings.rom).to_string(),
+                rom_hash: hash,
+                cpu_rate: settings.cpu_rate,
+                timers_rate: settings.timers_rate,
+                timing: settings.timing,
+                display_wait: settings.display_wait,
+                rng: settings.rng,
+                seed: Some(settings.seed),
+                frames: 0,
+                events: Vec::new(),
+                checkpoints: Vec::new(),
+            },
+            path: path.to_string(),
+            playing: false,
+            next_event: 0,
+            next_checkpoint: 0,
+            desynced: false,
+        })
+    }
+
+    pub fn play(path: &str) -> Result<Movie, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read movie {}: {}", path, err))?;
+        let file: MovieFile = serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid movie {}: {}", path, err))?;
+        if file.version != MOVIE_VERSION {
+            return Err(format!(
+                "Movie {} has version {}, only version {} is supported",
+                path, file.version, MOVIE_VERSION
+            ));
+        }
+
+        Ok(Movie {
+            file,
+            path: path.to_string(),
+            playing: true,
+            next_event: 0,
+            next_checkpoint: 0,
+            desynced: false,
+        })
+    }
+
+    // the movie only replays on the rom, clock, quirks and rng it was recorded with, so it brings its own
+    pub fn apply_settings(&self, settings: &mut ChipSettings) -> Result<(), String> {
+        let hash = rom_hash(&settings.rom)
+            .map_err(|err| format!("Failed to read ROM {}: {}", settings.rom, err))?;
+        if hash != self.file.rom_hash {
+            return Err(format!(
+                "Movie {} was recorded with a different ROM ({})",
+                self.path, self.file.rom
+            ));
+        }
+
+        if settings.cpu_rate != self.file.cpu_rate
+            || settings.timers_rate != self.file.timers_rate
+            || settings.timing != self.file.timing
+        {
+            log::info!(
+                "Using the movie's clock: {} Hz CPU, {} Hz timers, {:?} timing",
+                self.file.cpu_rate,
+                self.file.timers_rate,
+                self.file.timing
+            );
+        }
+        settings.cpu_rate = self.file.cpu_rate;
+        settings.timers_rate = self.file.timers_rate;
+        settings.timing = self.file.timing;
+        settings.display_wait = self.file.display_wait;
+        settings.rng = self.file.rng;
+        match self.file.seed {
+            Some(seed) => settings.seed = seed,
+            None => log::warn!("Movie {} has no RND seed, it may desync", self.path),
+        }
+        Ok(())
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    // length of the recording in frames
+    pub fn frames(&self) -> u64 {
+        self.file.frames
+    }
+
+    pub fn is_finished(&self, machine: &Machine) -> bool {
+        self.playing && machine.frame() >= self.file.frames
+    }
+
+    // call right before machine.run_frame(), after the frontend updated the keypad. Also every frame the machine
+    // doesn't run, e.g. while paused, so no key edge is lost
+    pub fn before_frame(&mut self, machine: &Machine) {
+        let frame = machine.frame();
+        let events = machine.take_key_events();
+
+        if !self.playing {
+            self.file.events.extend(events.iter().map(|event| MovieEvent {
+                frame,
+                key: event.key as u8,
+                pressed: event.pressed,
+            }));
+            return;
+        }
+
+        let mut keyboard = machine.keyboard().borrow_mut();
+        while let Some(event) = self.file.events.get(self.next_event) {
+            if event.frame > frame {
+                break;
+            }
+            if let Some(key) = Key::from_u8(event.key) {
+                keyboard.set_key(key, event.pressed);
+            }
+            self.next_event += 1;
+        }
+    }
+
+    // call right after machine.run_frame()
+    pub fn after_frame(&mut self, machine: &Machine) {
+        let frame = machine.frame();
+        if !frame.is_multiple_of(CHECKPOINT_INTERVAL) {
+            return;
+        }
+
+        if !self.playing {
+            self.file.checkpoints.push(Checkpoint {
+                frame,
+                checksum: machine.checksum(),
+            });
+            return;
+        }
+
+        while let Some(checkpoint) = self.file.checkpoints.get(self.next_checkpoint) {
+            if checkpoint.frame > frame {
+                break;
+            }
+            self.next_checkpoint += 1;
+            if checkpoint.frame == frame && checkpoint.checksum != machine.checksum() && !self.desynced {
+                log::error!("Movie {} desynced at frame {}", self.path, frame);
+                self.desynced = true;
+            }
+        }
+    }
+
+    // writes the recording, for playback it reports how it went
+    pub fn finish(mut self, machine: &Machine) -> Result<(), String> {
+        if self.playing {
+            if !self.desynced && machine.frame() >= self.file.frames {
+                log::info!("Movie {} played back in sync", self.path);
+            }
+            return Ok(());
+        }
+
+        self.file.frames = machine.frame();
+        let content = serde_json::to_string(&self.file).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, content)
+            .map_err(|err| format!("Failed to write movie {}: {}", self.path, err))?;
+        log::info!("Movie saved to {} ({} frames)", self.path, self.file.frames);
+        Ok(())
+    }
+}
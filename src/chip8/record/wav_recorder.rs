@@ -0,0 +1,116 @@
+use crate::chip8::io::beeper::{Beeper, BeeperSettings};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::fs::File;
+use std::io::BufWriter;
+
+// renders the buzzer into a WAV file, one timer tick at a time, without an audio device
+pub struct WavRecorder {
+    writer: WavWriter<BufWriter<File>>,
+    path: String,
+    beeper: Beeper,
+    sample_rate: u64,
+    timers_rate: u64,
+    ticks: u64,
+    buffer: Vec<f32>,
+}
+
+impl WavRecorder {
+    pub fn new(
+        path: &str,
+        beeper: BeeperSettings,
+        sample_rate: u32,
+        timers_rate: u64,
+    ) -> Result<WavRecorder, hound::Error> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let writer = WavWriter::create(path, spec)?;
+        // the file is the point of recording, --mute only silences the speakers
+        let beeper = BeeperSettings {
+            muted: false,
+            ..beeper
+        };
+
+        Ok(WavRecorder {
+            writer,
+            path: path.to_string(),
+            beeper: Beeper::new(beeper, sample_rate),
+            sample_rate: sample_rate as u64,
+            timers_rate,
+            ticks: 0,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // call right before every Timers::decrement, `on` is the buzzer state since the previous tick
+    pub fn tick(&mut self, on: bool) -> Result<(), hound::Error> {
+        let start = samples_before(self.ticks, self.sample_rate, self.timers_rate);
+        self.ticks += 1;
+        let end = samples_before(self.ticks, self.sample_rate, self.timers_rate);
+
+        self.buffer.resize((end - start) as usize, 0.0);
+        self.beeper.fill(&mut self.buffer, on);
+        for &sample in self.buffer.iter() {
+            self.writer.write_sample((sample * i16::MAX as f32) as i16)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), hound::Error> {
+        self.writer.finalize()
+    }
+}
+
+// samples in the file before the given tick. Counting from the first tick keeps the length exact even if the
+// rates don't divide
+fn samples_before(ticks: u64, sample_rate: u64, timers_rate: u64) -> u64 {
+    ticks * sample_rate / timers_rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples_per_tick(ticks: u64, sample_rate: u64, timers_rate: u64) -> Vec<u64> {
+        let before = |tick| samples_before(tick, sample_rate, timers_rate);
+        (0..ticks).map(|tick| before(tick + 1) - before(tick)).collect()
+    }
+
+    #[test]
+    fn ticks_get_equal_shares_when_the_rates_divide() {
+        assert_eq!(samples_per_tick(60, 44100, 60), vec![735; 60]);
+        assert_eq!(samples_per_tick(50, 48000, 50), vec![960; 50]);
+    }
+
+    #[test]
+    fn leftover_samples_are_spread_over_the_ticks() {
+        let samples = samples_per_tick(60, 1000, 60);
+        assert!(samples.iter().all(|&count| count == 16 || count == 17));
+        // a second of ticks is exactly a second of sound
+        assert_eq!(samples.iter().sum::<u64>(), 1000);
+        assert_eq!(samples_per_tick(7, 22050, 7).iter().sum::<u64>(), 22050);
+    }
+
+    #[test]
+    fn the_file_has_the_samples_of_every_tick() {
+        let path = std::env::temp_dir().join(format!("rustychip-test-{}.wav", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut recorder = WavRecorder::new(&path, BeeperSettings::default(), 44100, 60).unwrap();
+        for tick in 0..90 {
+            recorder.tick(tick % 2 == 0).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let reader = hound::WavReader::open(&path).unwrap();
+        let samples = reader.duration();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(samples, 90 * 735);
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+// the emulation advances in whole frames, see Machine::run_frame
+pub const FRAME_RATE: u32 = 60;
+// how far behind the clock may fall before it gives up catching up, e.g. after the window was dragged
+const MAX_LAG: Duration = Duration::from_millis(250);
+
+// paces frames in real time, small delays are made up by the following frames so the
+// average speed stays exact, long stalls are skipped instead of being replayed at once
+#[derive(Debug, Clone)]
+pub struct FrameClock {
+    next_frame: Instant,
+}
+
+impl FrameClock {
+    pub fn new() -> FrameClock {
+        FrameClock {
+            next_frame: Instant::now(),
+        }
+    }
+
+    // time left until the next frame is due, zero if it already is
+    pub fn remaining(&self) -> Duration {
+        self.next_frame.saturating_duration_since(Instant::now())
+    }
+
+    // call when a frame is run, speed > 1 makes frames come faster, below 1 slower
+    pub fn tick(&mut self, speed: f64) {
+        self.next_frame += Duration::from_secs_f64(1.0 / (FRAME_RATE as f64 * speed));
+        let now = Instant::now();
+        if now.saturating_duration_since(self.next_frame) > MAX_LAG {
+            log::debug!("Frame clock fell behind, skipping ahead");
+            self.next_frame = now;
+        }
+    }
+
+    pub fn wait(&self) {
+        std::thread::sleep(self.remaining());
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> FrameClock {
+        FrameClock::new()
+    }
+}
@@ -0,0 +1,221 @@
+use crate::chip8::io::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::chip8::io::filter::ScreenFilter;
+use crate::chip8::io::terminal_input::TerminalInput;
+use crate::chip8::machine::Machine;
+use crate::chip8::record::Movie;
+use crate::chip8::scheduler::FrameClock;
+use crate::chip8::ChipSettings;
+use crossterm::event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags};
+use crossterm::{cursor, execute, terminal};
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TerminalRender {
+    // ▀ with the top pixel in the foreground and the bottom one in the background, full colour
+    #[default]
+    HalfBlock,
+    // 2x4 pixels per character, half the size but only two colours
+    Braille,
+}
+
+// runs the machine in the terminal it was started from, for ssh sessions and boxes without a display server
+pub struct Terminal {
+    machine: Machine,
+    settings: ChipSettings,
+    render: TerminalRender,
+    screen: ScreenFilter,
+    input: TerminalInput,
+    movie: Option<Movie>,
+    // keyboard enhancement flags were pushed and have to be popped again
+    enhanced: bool,
+    beeping: bool,
+    last_output: String,
+}
+
+impl Terminal {
+    pub fn new(settings: ChipSettings, render: TerminalRender) -> std::io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = std::io::stdout();
+        if let Err(err) = execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(err);
+        }
+
+        // kitty-style terminals can report key releases, everything else gets the timed workaround
+        let enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if enhanced {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        let machine = Machine::new(&settings);
+        let screen = ScreenFilter::new(settings.filter, settings.colors, settings.phosphor_frames);
+        let input = TerminalInput::new(&settings.keymap, enhanced);
+
+        Ok(Terminal {
+            machine,
+            settings,
+            render,
+            screen,
+            input,
+            movie: None,
+            enhanced,
+            beeping: false,
+            last_output: String::new(),
+        })
+    }
+
+    pub fn start_movie(&mut self, movie: Movie) {
+        self.movie = Some(movie);
+    }
+
+    // until Esc or Ctrl+C
+    pub fn run(&mut self) {
+        let mut clock = FrameClock::new();
+        while !self.input.should_quit() {
+            self.input.read_events();
+            self.run_frame();
+            self.beep();
+            // the first frame is drawn even if it is blank
+            let dirty_rows = self.machine.take_dirty_rows();
+            if self.screen.present(&self.machine.display(), dirty_rows) || self.last_output.is_empty() {
+                self.draw();
+            }
+
+            clock.tick(1.0);
+            clock.wait();
+        }
+
+        if let Some(movie) = self.movie.take() {
+            if let Err(err) = movie.finish(&self.machine) {
+                log::error!("{}", err);
+            }
+        }
+    }
+
+    fn run_frame(&mut self) {
+        match &mut self.movie {
+            Some(movie) => {
+                if !movie.is_playing() {
+                    self.machine.keyboard().borrow_mut().update(&mut self.input);
+                }
+                movie.before_frame(&self.machine);
+                self.machine.run_frame();
+                movie.after_frame(&self.machine);
+                if movie.is_finished(&self.machine) {
+                    if let Some(movie) = self.movie.take() {
+                        if let Err(err) = movie.finish(&self.machine) {
+                            log::error!("{}", err);
+                        }
+                    }
+                }
+            }
+            None => {
+                self.machine.keyboard().borrow_mut().update(&mut self.input);
+                self.machine.take_key_events();
+                self.machine.run_frame();
+            }
+        }
+    }
+
+    // the terminal bell rings once whenever the sound timer starts
+    fn beep(&mut self) {
+        let beeping = self.machine.should_beep() && !self.settings.beeper.muted;
+        if beeping && !self.beeping {
+            // stdout is line buffered, without the flush the bell would wait for the next redraw
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(b"\x07");
+            let _ = stdout.flush();
+        }
+        self.beeping = beeping;
+    }
+
+    fn draw(&mut self) {
+        let output = match self.render {
+            TerminalRender::HalfBlock => self.half_blocks(),
+            TerminalRender::Braille => self.braille(),
+        };
+        if output == self.last_output {
+            return;
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(output.as_bytes());
+        let _ = stdout.flush();
+        self.last_output = output;
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        let rgba = self.screen.rgba();
+        let i = (x + y * DISPLAY_WIDTH) * 4;
+        (rgba[i], rgba[i + 1], rgba[i + 2])
+    }
+
+    fn half_blocks(&self) -> String {
+        let mut output = String::from("\x1b[H");
+        for y in (0..DISPLAY_HEIGHT).step_by(2) {
+            let mut last = None;
+            for x in 0..DISPLAY_WIDTH {
+                let colors = (self.pixel(x, y), self.pixel(x, y + 1));
+                // colours only change at sprite edges, most of a row needs no escape codes at all
+                if last != Some(colors) {
+                    let ((tr, tg, tb), (br, bg, bb)) = colors;
+                    let _ = write!(output, "\x1b[38;2;{};{};{};48;2;{};{};{}m", tr, tg, tb, br, bg, bb);
+                    last = Some(colors);
+                }
+                output.push('▀');
+            }
+            output.push_str("\x1b[0m\r\n");
+        }
+        output
+    }
+
+    fn braille(&self) -> String {
+        // dot bits of U+2800, by position within the 2x4 cell
+        const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+        let background = self.settings.colors[0];
+        let background = ((background >> 16) as u8, (background >> 8) as u8, background as u8);
+        let foreground = self.settings.colors[1];
+
+        let mut output = String::from("\x1b[H");
+        for y in (0..DISPLAY_HEIGHT).step_by(4) {
+            let _ = write!(
+                output,
+                "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                (foreground >> 16) as u8,
+                (foreground >> 8) as u8,
+                foreground as u8,
+                background.0,
+                background.1,
+                background.2
+            );
+            for x in (0..DISPLAY_WIDTH).step_by(2) {
+                let mut cell = 0x2800;
+                for (dy, row) in DOTS.iter().enumerate() {
+                    for (dx, dot) in row.iter().enumerate() {
+                        if self.pixel(x + dx, y + dy) != background {
+                            cell |= dot;
+                        }
+                    }
+                }
+                output.push(char::from_u32(cell).unwrap_or(' '));
+            }
+            output.push_str("\x1b[0m\r\n");
+        }
+        output
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        if self.enhanced {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
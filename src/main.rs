@@ -1,108 +1,476 @@
 mod chip8;
 
+use chip8::bench::BenchLimit;
+use chip8::chip8::{open_window, rom_hash, Exit};
+use chip8::config::{Config, SettingsLayer};
+use chip8::database::RomDatabase;
+use chip8::cpu::rng::RngKind;
+use chip8::cpu::timing::Timing;
+use chip8::io::beeper::Waveform;
+use chip8::io::filter::DisplayFilter;
+use chip8::io::keymap::Layout;
+use chip8::io::palette::Palette;
+use chip8::io::scaling::Scaling;
+use chip8::launcher::Launcher;
+use chip8::library::{find_rom, RomLibrary};
+use chip8::platform::Platform;
+use chip8::record::Movie;
+use chip8::terminal::TerminalRender;
 use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Frontend {
+    // raylib window
+    Window,
+    // ansi colours in the terminal, works over ssh
+    Terminal,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Run the ROM headless without any pauses and report how fast it is emulated
+    Bench(BenchArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    /// Stop after this many instructions
+    #[clap(long, conflicts_with = "seconds")]
+    instructions: Option<u64>,
+
+    /// Stop after this many seconds [default: 5]
+    #[clap(long)]
+    seconds: Option<f64>,
+}
 
 #[derive(Parser, Debug)]
 #[command(about, long_about = None)]
 struct Args {
-    /// ROM file to load
-    #[clap(short, long)]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// ROM file to load, a path or a name in the ROM directories. Without it the window shows a ROM menu
+    #[clap(short, long, global = true)]
     rom: Option<String>,
 
-    /// Window width in pixels, default = 640. Ff not set to multiples of 64, it will be rounded down.
+    /// More directories to look for ROMs in, besides rom/
+    #[clap(long, global = true)]
+    rom_dir: Vec<String>,
+
+    /// programs.json of the CHIP-8 community database, for ROM titles, descriptions and platforms
+    #[clap(long, global = true)]
+    database: Option<String>,
+
+    /// Initial window width in pixels, default = 640. The window can be resized.
     #[clap(short, long)]
     width: Option<u32>,
 
-    ///  Window size in pixels, default = 320. It's enough to set only one of the dimensions, the other will be calculated automatically. If not set to multiples of 32, it will be rounded down.
+    ///  Initial window height in pixels, default = 320. It's enough to set only one of the dimensions, the other will be calculated automatically.
     #[clap(long)]
     height: Option<u32>,
 
-    /// Foreground color. The background color will be the opposite of this color.
-    #[clap(short, long, default_value = "0xFFFFFF")]
-    fg_color: String,
+    /// Foreground color: 0xRRGGBB, #RRGGBB, #RGB or a CSS color name. Without --palette the background will be the opposite of this color. [default: white]
+    #[clap(short, long)]
+    fg_color: Option<String>,
 
-    /// Background color. Default = inverse of fg_color
+    /// Background color. Default = inverse of fg_color, or the palette's background
     #[clap(short, long)]
     bg_color: Option<String>,
 
-    /// CPU Clock speed (Hz)
-    #[clap(short, long, default_value = "500")]
-    cpu_clock: u32,
+    /// Color palette, --fg-color and --bg-color override its first two colors [default: default]
+    #[clap(long, value_enum)]
+    palette: Option<Palette>,
+
+    /// Display filter against flicker [default: none]
+    #[clap(long, value_enum)]
+    filter: Option<DisplayFilter>,
+
+    /// How many frames pixels take to fade out with --filter phosphor [default: 5]
+    #[clap(long)]
+    phosphor_frames: Option<u32>,
+
+    /// How the display is scaled to the window, the rest is letterboxed [default: integer]
+    #[clap(long, value_enum)]
+    scaling: Option<Scaling>,
+
+    /// Width / height of one pixel, e.g. 0.5 for tall pixels [default: 1.0]
+    #[clap(long)]
+    pixel_aspect: Option<f32>,
+
+    /// Draw lines between the pixels
+    #[clap(long)]
+    grid: bool,
+
+    /// Start in fullscreen, F11 toggles it
+    #[clap(long)]
+    fullscreen: bool,
+
+    /// Show speed, fps and the keypad on top of the game, F10 toggles it
+    #[clap(long)]
+    overlay: bool,
+
+    /// Open the debugger panels next to the game, F12 toggles them
+    #[clap(long)]
+    debugger: bool,
+
+    /// Start paused, P resumes and N advances one frame at a time
+    #[clap(long)]
+    paused: bool,
+
+    /// How many times faster the emulation runs in fast forward [default: 4]
+    #[clap(long)]
+    fast_forward_speed: Option<f32>,
+
+    /// Speed of slow motion, e.g. 0.25 for a quarter of the normal speed [default: 0.25]
+    #[clap(long)]
+    slow_motion_speed: Option<f32>,
+
+    /// CPU Clock speed (Hz) [default: 500]
+    #[clap(short, long, global = true)]
+    cpu_clock: Option<u32>,
+
+    /// Instructions per 60 Hz frame, like Octo's tickrate. Instead of --cpu-clock
+    #[clap(short, long, conflicts_with = "cpu_clock", global = true)]
+    tickrate: Option<u32>,
 
-    /// Timers rate (Hz). CPU to timers rate should be 500/60
+    /// Clock of the VIP's 1802 CPU (Hz) with VIP timing, where --cpu-clock doesn't apply [default: 1760640]
+    #[clap(long, global = true)]
+    vip_clock: Option<u32>,
+
+    /// Timers rate (Hz). Default = once per 60 Hz frame, there is little reason to change it
     #[clap(long)]
     timers_rate: Option<u32>,
     
-    /// Swap Y and Z keys
+    /// Machine the ROM was written for [default: chip8]
+    #[clap(short, long, value_enum, global = true)]
+    platform: Option<Platform>,
+
+    /// How long instructions take: a fixed number per frame, or what they took on the VIP [default: instructions, vip for --platform vip]
+    #[clap(long, value_enum, global = true)]
+    timing: Option<Timing>,
+
+    /// Display wait quirk, DRW waits for the next frame so at most 60 sprites are drawn per second [default: false, true for --platform vip]
+    #[clap(long, global = true)]
+    display_wait: Option<bool>,
+
+    /// Keyboard layout. The letter layouts keep the hex keypad at the same key positions, numpad moves it [default: qwerty]
+    #[clap(short, long, value_enum)]
+    layout: Option<Layout>,
+
+    /// Swap Y and Z keys, same as --layout qwertz
     #[clap(short = 'z', long)]
     swap_yz: bool,
+
+    /// Key binding file (TOML, or JSON with the .json extension)
+    #[clap(short, long)]
+    keymap: Option<String>,
+
+    /// Random number generator behind the RND instruction [default: xorshift, vip for --platform vip]
+    #[clap(long, value_enum)]
+    rng: Option<RngKind>,
+
+    /// Seed for the random number generator. Default = a different one every run
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Disable sound
+    #[clap(long)]
+    mute: bool,
+
+    /// Beep frequency (Hz) [default: 440]
+    #[clap(long)]
+    beep_frequency: Option<f32>,
+
+    /// Beep volume, 0 - 100 % [default: 25]
+    #[clap(long)]
+    volume: Option<u8>,
+
+    /// Beep waveform [default: square]
+    #[clap(long, value_enum)]
+    waveform: Option<Waveform>,
+
+    /// Record the display into an animated GIF from the start. F9 toggles recording in the window.
+    #[clap(long)]
+    gif: Option<String>,
+
+    /// Render the sound into a WAV file. Only in headless mode
+    #[clap(long)]
+    wav: Option<String>,
+
+    /// Sample rate of the WAV file (Hz)
+    #[clap(long, default_value = "44100")]
+    sample_rate: u32,
+
+    /// Where to show the emulator
+    #[clap(long, value_enum, default_value = "window")]
+    frontend: Frontend,
+
+    /// How the terminal frontend draws pixels
+    #[clap(long, value_enum, default_value = "half-block")]
+    terminal_render: TerminalRender,
+
+    /// Run without a window, as fast as possible, for the number of frames set by --frames
+    #[clap(long)]
+    headless: bool,
+
+    /// Number of 60 Hz frames to run in headless mode [default: 600, or the length of the movie played]
+    #[clap(long)]
+    frames: Option<u64>,
+
+    /// Record the keypad into a movie file
+    #[clap(long, conflicts_with = "play")]
+    record: Option<String>,
+
+    /// Play a movie file back, in the window or headless
+    #[clap(long)]
+    play: Option<String>,
+
+    /// Config file. Default = $XDG_CONFIG_HOME/rustychip/config.toml, if it exists
+    #[clap(long, global = true)]
+    config: Option<String>,
 }
 
-fn args_to_settings(args: Args) -> chip8::ChipSettings {
-    let size: (u32, u32) = match (args.width, args.height) {
-        (Some(width), Some(height)) => (width, height),
-        (Some(width), None) => (width, 0),
-        (None, Some(height)) => (0, height),
-        (None, None) => (0, 0),
-    };
-    let fg_color = u32::from_str_radix(&args.fg_color[2..], 16).expect("Invalid foreground color");
-    let bg_color = match args.bg_color {
-        Some(bg_color) => {
-            u32::from_str_radix(&bg_color[2..], 16).expect("Invalid background color")
-        }
-        None => !fg_color,
-    };
-    let cpu_rate = args.cpu_clock as u64;
-    let timers_rate = match args.timers_rate {
-        Some(rate) => {
-            if rate == 0 {
-                (cpu_rate as f32 / 500f32 * 60f32) as u64
-            }
-            else {
-                rate as u64
+// the command line is the top settings layer, only what was given overrides the config file
+fn args_to_layer(args: &Args) -> SettingsLayer {
+    SettingsLayer {
+        width: args.width,
+        height: args.height,
+        fg_color: args.fg_color.clone(),
+        bg_color: args.bg_color.clone(),
+        palette: args.palette,
+        filter: args.filter,
+        phosphor_frames: args.phosphor_frames,
+        scaling: args.scaling,
+        pixel_aspect: args.pixel_aspect,
+        grid: args.grid.then_some(true),
+        fullscreen: args.fullscreen.then_some(true),
+        overlay: args.overlay.then_some(true),
+        debugger: args.debugger.then_some(true),
+        fast_forward_speed: args.fast_forward_speed,
+        slow_motion_speed: args.slow_motion_speed,
+        cpu_clock: args.cpu_clock,
+        tickrate: args.tickrate,
+        vip_clock: args.vip_clock,
+        timers_rate: args.timers_rate,
+        platform: args.platform,
+        timing: args.timing,
+        display_wait: args.display_wait,
+        layout: if args.swap_yz { Some(Layout::Qwertz) } else { args.layout },
+        keymap: args.keymap.clone(),
+        rng: args.rng,
+        seed: args.seed,
+        mute: args.mute.then_some(true),
+        beep_frequency: args.beep_frequency,
+        volume: args.volume,
+        waveform: args.waveform,
+    }
+}
+
+// rom/ first, then the config file's directories, then the command line's
+fn rom_dirs(args: &Args, config: &Config) -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("rom")];
+    dirs.extend(config.rom_dirs.iter().map(PathBuf::from));
+    dirs.extend(args.rom_dir.iter().map(PathBuf::from));
+    dirs
+}
+
+fn load_database(args: &Args, config: &Config) -> Result<RomDatabase, String> {
+    match args.database.as_ref().or(config.database.as_ref()) {
+        Some(path) => RomDatabase::load(path),
+        None => Ok(RomDatabase::default()),
+    }
+}
+
+fn args_to_settings(
+    args: &Args,
+    rom: &str,
+    config: &Config,
+    database: &RomDatabase,
+) -> Result<chip8::ChipSettings, String> {
+    let mut builder = chip8::ChipSettings::builder(rom.to_string()).layer(&config.defaults);
+    // the database knows the platform of many roms, the rom's own section can still override it
+    let platform = rom_hash(rom)
+        .ok()
+        .and_then(|hash| database.get(&hash).and_then(|info| info.platform()));
+    if platform.is_some() {
+        builder = builder.layer(&SettingsLayer {
+            platform,
+            ..SettingsLayer::default()
+        });
+    }
+    if let Some(layer) = config.rom_layer(rom) {
+        builder = builder.layer(layer);
+    }
+    builder.layer(&args_to_layer(args)).build()
+}
+
+// no rom given: pick one in the window, play it, Esc goes back to the menu
+fn run_launcher(args: &Args, config: &Config, database: &RomDatabase, dirs: &[PathBuf]) {
+    if args.gif.is_some() || args.record.is_some() || args.play.is_some() {
+        log::warn!("--gif, --record and --play need a ROM given with --rom, ignoring them");
+    }
+    let library = RomLibrary::scan(dirs, config, database);
+
+    // the window is opened before any rom is known, so only the defaults apply to it
+    let mut defaults = config.defaults.clone();
+    defaults.merge(&args_to_layer(args));
+    let size = chip8::ChipSettings::fix_size(
+        (defaults.width.unwrap_or(0), defaults.height.unwrap_or(0)),
+        defaults.pixel_aspect.unwrap_or(1.0),
+    );
+    let mut window = open_window(size, "Rusty Chip-8", defaults.fullscreen.unwrap_or(false));
+
+    let mut launcher = Launcher::new(&library);
+    while let Some(rom) = launcher.run(&mut window) {
+        let settings = match args_to_settings(args, &rom, config, database) {
+            Ok(settings) => settings,
+            Err(err) => {
+                log::error!("{}", err);
+                launcher.show_error(err);
+                continue;
             }
+        };
+        let mut chip8 = chip8::Chip8::from_menu(settings, window);
+        if args.paused {
+            chip8.pause();
+        }
+        let exit = chip8.run();
+        window = chip8.into_window();
+        if exit == Exit::Closed {
+            break;
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let config = Config::load(args.config.as_deref()).and_then(|config| {
+        let database = load_database(&args, &config)?;
+        Ok((config, database))
+    });
+    let (config, database) = match config {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            log::error!("{}", err);
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
-        None => (cpu_rate as f32 / 500f32 * 60f32) as u64,
     };
-    let beep = true;
-    let rom = match args.rom {
-        Some(rom) => {
-            let roms_to_try = vec![
-                rom.clone(),
-                format!("{}.ch8", rom),
-                format!("rom/{}", rom),
-                format!("rom/{}.ch8", rom),
-            ];
-            let mut found_rom = String::new();
-            for rom in roms_to_try {
-                if std::path::Path::new(&rom).exists() {
-                    found_rom = rom;
-                    break;
-                }
-            }
-            if found_rom.is_empty() {
+    let dirs = rom_dirs(&args, &config);
+
+    let rom = match &args.rom {
+        Some(rom) => match find_rom(rom, &dirs) {
+            Some(rom) => rom,
+            None => {
                 log::error!("ROM file not found: {}", rom);
-                println!("ROM file not found: {}", rom);
+                eprintln!("ROM file not found: {}", rom);
                 std::process::exit(1);
             }
-            found_rom
+        },
+        // the menu lives in the window
+        None if args.command.is_none() && !args.headless && args.frontend == Frontend::Window => {
+            run_launcher(&args, &config, &database, &dirs);
+            return;
         }
         None => {
             log::error!("No ROM file provided");
-            println!("No ROM file provided");
+            eprintln!("No ROM file provided");
             std::process::exit(1);
         }
     };
 
-    chip8::ChipSettings::new(rom, size, cpu_rate, timers_rate, (fg_color, bg_color), args.swap_yz, beep)
-}
+    let mut settings = match args_to_settings(&args, &rom, &config, &database) {
+        Ok(settings) => settings,
+        Err(err) => {
+            log::error!("{}", err);
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(Command::Bench(bench)) = &args.command {
+        let limit = match (bench.instructions, bench.seconds) {
+            (Some(instructions), _) => BenchLimit::Instructions(instructions),
+            (None, seconds) => BenchLimit::Seconds(seconds.unwrap_or(5.0)),
+        };
+        let report = chip8::bench::Bench::new(settings).run(limit);
+        print!("{}", report);
+        return;
+    }
 
-// 6377
-fn main() {
-    let args = Args::parse();
+    let movie = match (&args.record, &args.play) {
+        (Some(path), _) => Some(Movie::record(path, &settings)),
+        (_, Some(path)) => Some(Movie::play(path).and_then(|movie| {
+            movie.apply_settings(&mut settings)?;
+            Ok(movie)
+        })),
+        _ => None,
+    };
+    let movie = match movie.transpose() {
+        Ok(movie) => movie,
+        Err(err) => {
+            log::error!("{}", err);
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if args.headless {
+        let frames = match (args.frames, &movie) {
+            (Some(frames), _) => frames,
+            (None, Some(movie)) if movie.is_playing() => movie.frames(),
+            _ => 600,
+        };
+        let mut headless = chip8::Headless::new(settings);
+        if let Some(path) = &args.gif {
+            headless.start_gif_recording(path);
+        }
+        if let Some(path) = &args.wav {
+            headless.start_wav_recording(path, args.sample_rate);
+        }
+        if let Some(movie) = movie {
+            headless.start_movie(movie);
+        }
+        headless.run(frames);
+        return;
+    }
 
-    println!("Hello, Chip-8!");
+    if args.wav.is_some() {
+        log::warn!("WAV rendering is only supported in headless mode, ignoring --wav");
+    }
+
+    if args.frontend == Frontend::Terminal {
+        if args.gif.is_some() {
+            log::warn!("GIF recording is not supported in the terminal, ignoring --gif");
+        }
+        if args.paused {
+            log::warn!("The terminal can't be paused, ignoring --paused");
+        }
+        let mut terminal = match chip8::Terminal::new(settings, args.terminal_render) {
+            Ok(terminal) => terminal,
+            Err(err) => {
+                log::error!("Failed to set up the terminal: {}", err);
+                eprintln!("Failed to set up the terminal: {}", err);
+                std::process::exit(1);
+            }
+        };
+        if let Some(movie) = movie {
+            terminal.start_movie(movie);
+        }
+        terminal.run();
+        return;
+    }
 
-    let mut chip8 = chip8::Chip8::new(args_to_settings(args));
+    let mut chip8 = chip8::Chip8::new(settings);
+    if args.paused {
+        chip8.pause();
+    }
+    if let Some(path) = &args.gif {
+        chip8.start_gif_recording(path);
+    }
+    if let Some(movie) = movie {
+        chip8.start_movie(movie);
+    }
     chip8.run();
 }